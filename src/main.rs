@@ -1,136 +1,416 @@
 extern crate colored;
 use colored::*;
 
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::process;
 use std::rc::Rc;
 
 mod sloth;
 use sloth::*;
 
+mod repl;
+
+const EXIT_USAGE:   i32 = 1;
+const EXIT_PARSE:   i32 = 2;
+const EXIT_CHECK:   i32 = 3;
+const EXIT_COMPILE: i32 = 4;
+const EXIT_RUNTIME: i32 = 5;
+const EXIT_LOAD:    i32 = 6;
+
+const USAGE: &str = "usage: sloth [repl] | [run | tokens | ast | check | bytecode | compile] <file | ->";
+
+const BYTECODE_EXTENSION: &str = ".slothc";
+
 fn main() {
-    let test = r#"
-hmm := {
-  |n| n
-}    
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.is_empty() || args.len() == 1 && args[0] == "repl" {
+        repl::Repl::new().run();
+        return
+    }
+
+    let (mode, path) = match args.len() {
+        1 => ("run", args[0].as_str()),
+        2 => (args[0].as_str(), args[1].as_str()),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(EXIT_USAGE)
+        },
+    };
+
+    // compiled files are run as they are, there is no source to go back to
+    if mode == "run" && path.ends_with(BYTECODE_EXTENSION) {
+        if let Err(code) = run_bytecode(path) {
+            process::exit(code)
+        }
 
-fib := {
-  |n| n
+        return
+    }
+
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(why)   => {
+            eprintln!("{}", format!("sloth: can't read {}: {}", path, why).red());
+            process::exit(EXIT_USAGE)
+        },
+    };
+
+    let result = match mode {
+        "run"      => run(&source),
+        "tokens"   => dump_tokens(&source),
+        "ast"      => dump_ast(&source),
+        "check"    => dump_check(&source),
+        "bytecode" => dump_bytecode(&source),
+        "compile"  => compile_file(&source, path),
+        _          => {
+            eprintln!("{}", USAGE);
+            Err(EXIT_USAGE)
+        },
+    };
+
+    if let Err(code) = result {
+        process::exit(code)
+    }
 }
 
-print hmm fib 10
-    "#;
+fn read_source(path: &str) -> io::Result<String> {
+    let mut source = String::new();
 
-    let lexer = lexer(&mut test.chars());
+    if path == "-" {
+        io::stdin().read_to_string(&mut source)?;
+    } else {
+        File::open(path)?.read_to_string(&mut source)?;
+    }
 
-    let traveler   = Traveler::new(lexer.collect());
-    let mut parser = Parser::new(traveler);
+    Ok(source)
+}
 
-    match parser.parse() {
-        Err(err)  => match err {
-            ParserError {ref value, ref position} => {
-                match *position {
-                    Some(ref pos) => {
-                        let mut lines = test.lines();
-
-                        for i in 0 .. pos.line - 1 {
-                            if i == pos.line - 2 {
-                                let source_pos = format!("ln {}      | ", pos.line - 1).yellow();
-                                match lines.next() {
-                                    Some(line) => println!("{}{}", source_pos, line),
-                                    None       => unreachable!(),
-                                }
-                            } else {
-                                lines.next();
-                            }
-                        }
-
-                        let source_pos = format!("ln {}, cl {}| ", pos.line, pos.col).yellow();
-
-                        match lines.next() {
-                            Some(line) => println!("{}{}", source_pos, line),
-                            None       => unreachable!(),
-                        }
-
-                        let mut error = String::from("");
-
-                        for _ in 0 .. pos.col + source_pos.len() {
-                            error.push_str(" ")
-                        }
-
-                        error.push_str("^ ");
-
-                        match *value {
-                            ParserErrorValue::Constant(ref a) => error.push_str(a),
-                        }
-
-                        println!("{}", error.red());
-                    },
-
-                    None => (),
+fn run(source: &str) -> Result<(), i32> {
+    let statements = parse(source)?;
+    let root       = Expression::Block(statements);
+
+    check(source, &root)?;
+
+    let mut vm = VirtualMachine::new();
+    let compiled = compile(source, &mut vm, &root)?;
+
+    execute(source, &mut vm, &compiled).map(|_| ())
+}
+
+fn run_bytecode(path: &str) -> Result<(), i32> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(why)  => {
+            eprintln!("{}", format!("sloth: can't read {}: {}", path, why).red());
+            return Err(EXIT_USAGE)
+        },
+    };
+
+    let mut vm = VirtualMachine::new();
+
+    let mut compiled = match load_bytecode(&mut vm, &bytes) {
+        Ok(compiled) => compiled,
+        Err(err)     => {
+            println!("{}", format!("{}: {}", path, err).red());
+            return Err(EXIT_LOAD)
+        },
+    };
+
+    if let Err(err) = verify(&mut compiled) {
+        println!("{}", format!("{}: invalid bytecode: {}", path, err).red());
+        return Err(EXIT_LOAD)
+    }
+
+    execute("", &mut vm, &compiled).map(|_| ())
+}
+
+// `program.sloth` is compiled to `program.slothc`, stdin to stdout
+fn compile_file(source: &str, path: &str) -> Result<(), i32> {
+    let root = Expression::Block(parse(source)?);
+
+    check(source, &root)?;
+
+    let mut vm = VirtualMachine::new();
+    let bytes  = compile(source, &mut vm, &root)?.to_bytes();
+
+    let written = if path == "-" {
+        io::stdout().write_all(&bytes)
+    } else {
+        let out = format!("{}{}", path.trim_end_matches(".sloth"), BYTECODE_EXTENSION);
+        fs::write(&out, &bytes)
+    };
+
+    written.map_err(|why| {
+        eprintln!("{}", format!("sloth: can't write bytecode: {}", why).red());
+        EXIT_USAGE
+    })
+}
+
+pub fn execute(source: &str, vm: &mut VirtualMachine, compiled: &CompiledBlock) -> Result<Value, i32> {
+    match vm.execute(compiled) {
+        Ok(value) => Ok(value),
+        Err(RuntimeError { ref value, position, op, ref operands, ref trace }) => {
+            let RuntimeErrorValue::Constant(ref message) = *value;
+
+            report(source, position, message);
+
+            if let Some(op) = op {
+                let operands = operands.iter().map(|v| format!("{}", v)).collect::<Vec<String>>();
+
+                if operands.is_empty() {
+                    println!("{}", format!("while running {}", op.name()).yellow())
+                } else {
+                    println!("{}", format!("while running {} on {}", op.name(), operands.join(", ")).yellow())
                 }
+            }
+
+            if trace.iter().any(|frame| frame.function.is_some()) {
+                print_trace(source, trace)
+            }
+
+            Err(EXIT_RUNTIME)
+        },
+    }
+}
+
+fn dump_tokens(source: &str) -> Result<(), i32> {
+    let print = |token: &Token| println!("{:<10} {:<14} {:?}", format!("{}", token.position), format!("{:?}", token.token_type), token.content());
+
+    let mut lexer  = lexer(0, &mut source.chars());
+    let mut failed = false;
+
+    // comments are listed ahead of the token they're attached to
+    for token in Layout::new(lexer.by_ref()) {
+        match token {
+            Ok(token) => {
+                token.trivia().iter().for_each(print);
+                print(&token)
+            },
+            Err(err)  => {
+                report_lex(source, &err);
+                failed = true
+            },
+        }
+    }
+
+    lexer.trailing_trivia().iter().for_each(print);
+
+    if failed {
+        Err(EXIT_PARSE)
+    } else {
+        Ok(())
+    }
+}
+
+fn dump_ast(source: &str) -> Result<(), i32> {
+    for statement in parse(source)? {
+        println!("{:#?}", statement)
+    }
+
+    Ok(())
+}
+
+fn dump_check(source: &str) -> Result<(), i32> {
+    let root = Expression::Block(parse(source)?);
+
+    let (symtab, typetab) = check(source, &root)?;
+
+    println!("{}", "symbols".yellow());
+    print!("{:?}", symtab);
+    println!("{}", "types".yellow());
+    print!("{:?}", typetab);
+
+    Ok(())
+}
+
+fn dump_bytecode(source: &str) -> Result<(), i32> {
+    let root = Expression::Block(parse(source)?);
+
+    check(source, &root)?;
+
+    let mut vm = VirtualMachine::new();
+    print!("{}", compile(source, &mut vm, &root)?);
+
+    Ok(())
+}
+
+// every malformed token is reported before giving up on the source
+pub fn parse(source: &str) -> Result<Vec<Statement>, i32> {
+    let mut tokens = Vec::new();
+    let mut failed = false;
+
+    for token in Layout::new(lexer(0, &mut source.chars())) {
+        match token {
+            Ok(token) => tokens.push(token),
+            Err(err)  => {
+                report_lex(source, &err);
+                failed = true
             },
+        }
+    }
+
+    if failed {
+        return Err(EXIT_PARSE)
+    }
+
+    let traveler   = Traveler::new(tokens);
+    let mut parser = Parser::new(traveler);
+
+    match parser.parse() {
+        Ok(statements) => Ok(statements),
+        Err(ParserError { ref value, ref position }) => {
+            let ParserErrorValue::Constant(ref message) = *value;
+
+            report(source, *position, message);
+            Err(EXIT_PARSE)
         },
-        Ok(stuff) => {
-            let symtab  = Rc::new(SymTab::new_global());
-            let typetab = Rc::new(TypeTab::new_global());
-            
-            let root = Expression::Block(stuff);
-
-            match root.visit(&symtab, &typetab) {
-                Err(err) => match err {
-                    CheckError {ref value, ref position} => {
-                        match *position {
-                            Some(ref pos) => {
-                                let mut lines = test.lines();
-
-                                for i in 0 .. pos.line - 1 {
-                                    if i == pos.line - 2 {
-                                        let source_pos = format!("          | ").yellow();
-                                        match lines.next() {
-                                            Some(line) => println!("{}{}", source_pos, line),
-                                            None       => unreachable!(),
-                                        }
-                                    } else {
-                                        lines.next();
-                                    }
-                                }
-
-                                let source_pos = format!("ln {}, cl {}| ", pos.line, pos.col).yellow();
-
-                                match lines.next() {
-                                    Some(line) => println!("{}{}", source_pos, line),
-                                    None       => unreachable!(),
-                                }
-
-                                let mut error = String::from("");
-
-                                for _ in 0 .. pos.col + source_pos.len() {
-                                    error.push_str(" ")
-                                }
-
-                                error.push_str("^ ");
-
-                                match *value {
-                                    CheckErrorValue::Constant(ref a) => error.push_str(a),
-                                }
-                                
-                                println!("{}", error.red());
-                                
-                            },
-                            
-                            None => (),
-                        }
-                    },
-                },
-                _ => {
-                    let mut vm       = VirtualMachine::new();
-                    let mut compiler = Compiler::new(vm.clone());
-
-                    match compiler.compile(&root) {
-                        Ok(compiled) => vm.execute(&compiled),
-                        Err(err)     => println!("{}", err),
-                    }
-                },
-            }
+    }
+}
+
+fn check(source: &str, root: &Expression) -> Result<(Rc<SymTab>, Rc<TypeTab>), i32> {
+    let symtab  = Rc::new(SymTab::new_global());
+    let typetab = Rc::new(TypeTab::new_global());
+
+    check_in(source, root, &symtab, &typetab)?;
+
+    Ok((symtab, typetab))
+}
+
+pub fn check_in(source: &str, root: &Expression, symtab: &Rc<SymTab>, typetab: &Rc<TypeTab>) -> Result<(), i32> {
+    type_in(source, root, symtab, typetab).map(|_| ())
+}
+
+pub fn type_in(source: &str, root: &Expression, symtab: &Rc<SymTab>, typetab: &Rc<TypeTab>) -> Result<Type, i32> {
+    let result = root.get_type(symtab, typetab);
+
+    for CheckError { ref value, ref position } in symtab.take_warnings() {
+        let CheckErrorValue::Constant(ref message) = *value;
+
+        excerpt(source, *position, &format!("warning: {}", message), Color::Yellow)
+    }
+
+    match result {
+        Ok(t)    => Ok(t),
+        Err(CheckError { ref value, ref position }) => {
+            let CheckErrorValue::Constant(ref message) = *value;
+
+            report(source, *position, message);
+            Err(EXIT_CHECK)
+        },
+    }
+}
+
+fn compile(source: &str, vm: &mut VirtualMachine, root: &Expression) -> Result<CompiledBlock, i32> {
+    compile_in(source, &mut Compiler::new(vm.clone()), root)
+}
+
+pub fn compile_in(source: &str, compiler: &mut Compiler, root: &Expression) -> Result<CompiledBlock, i32> {
+    match compiler.compile(root) {
+        Ok(compiled) => Ok(compiled),
+        Err(CompileError { ref value, position }) => {
+            let CompileErrorValue::Constant(ref message) = *value;
+
+            report(source, position, message);
+            Err(EXIT_COMPILE)
+        },
+    }
+}
+
+// innermost call first, runs of one frame recursing into itself are folded
+fn print_trace(source: &str, trace: &[TraceFrame]) {
+    let lines: Vec<&str> = source.lines().collect();
+
+    let describe = |frame: &TraceFrame| {
+        let function = frame.function.clone().unwrap_or(String::from("<main>"));
+
+        let at = match frame.arm {
+            Some(arm) => format!("{}, arm {}", function, arm),
+            None      => function,
+        };
+
+        match frame.position {
+            Some(pos) => format!("{:<20} ln {:<4}| {}", at, pos.line, lines.get(pos.line.wrapping_sub(1)).map_or("", |l| l.trim())),
+            None      => at,
+        }
+    };
+
+    println!("{}", "stack trace:".yellow());
+
+    let mut previous = None;
+    let mut repeated = 0;
+
+    for frame in trace {
+        let line = describe(frame);
+
+        if previous.as_ref() == Some(&line) {
+            repeated += 1;
+            continue
         }
+
+        if repeated > 0 {
+            println!("  ... {} more like the above", repeated);
+            repeated = 0
+        }
+
+        println!("  {}", line.trim_end());
+        previous = Some(line)
+    }
+
+    if repeated > 0 {
+        println!("  ... {} more like the above", repeated)
     }
 }
+
+fn report(source: &str, position: Option<TokenPosition>, message: &str) {
+    excerpt(source, position, message, Color::Red)
+}
+
+fn report_lex(source: &str, err: &LexError) {
+    let LexErrorValue::Constant(ref message) = err.value;
+
+    report(source, Some(err.position), message)
+}
+
+// prints the offending line, the one before it and a caret under the column
+fn excerpt(source: &str, position: Option<TokenPosition>, message: &str, color: Color) {
+    let pos = match position {
+        Some(pos) => pos,
+        None      => {
+            println!("{}", message.color(color));
+            return
+        },
+    };
+
+    let lines: Vec<&str> = source.lines().collect();
+
+    // compiled files are run without their source, so there is only the position to give
+    if pos.line == 0 || pos.line > lines.len() {
+        println!("{}{}", format!("ln {}, cl {}| ", pos.line, pos.col).yellow(), message.color(color));
+        return
+    }
+
+    if pos.line > 1 {
+        if let Some(line) = lines.get(pos.line - 2) {
+            println!("{}{}", format!("ln {}      | ", pos.line - 1).yellow(), line)
+        }
+    }
+
+    let source_pos = format!("ln {}, cl {}| ", pos.line, pos.col);
+
+    if let Some(line) = lines.get(pos.line - 1) {
+        println!("{}{}", source_pos.yellow(), line)
+    }
+
+    let mut error = String::new();
+
+    for _ in 0 .. pos.col + source_pos.len() {
+        error.push(' ')
+    }
+
+    error.push_str("^ ");
+    error.push_str(message);
+
+    println!("{}", error.color(color));
+}
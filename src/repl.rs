@@ -0,0 +1,223 @@
+use colored::*;
+
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use sloth::*;
+
+use super::{parse, check_in, type_in, compile_in, execute};
+
+pub struct Repl {
+    symtab:   Rc<SymTab>,
+    typetab:  Rc<TypeTab>,
+    vm:       VirtualMachine,
+    compiler: Compiler,
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        let vm = VirtualMachine::new();
+
+        Repl {
+            symtab:   Rc::new(SymTab::new_global()),
+            typetab:  Rc::new(TypeTab::new_global()),
+            compiler: Compiler::new(vm.clone()),
+            vm,
+        }
+    }
+
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+
+        loop {
+            let mut input = String::new();
+
+            loop {
+                print!("{}", if input.is_empty() { ">> " } else { ".. " });
+                io::stdout().flush().unwrap();
+
+                match lines.next() {
+                    Some(Ok(line)) => {
+                        let blank = line.trim().is_empty();
+
+                        input.push_str(&line);
+                        input.push('\n');
+
+                        // a blank line ends an arm or a block, but not an open brace or comment
+                        if !needs_more(&input) || blank && !unclosed(&input) {
+                            break
+                        }
+                    },
+
+                    _ => {
+                        println!();
+                        return
+                    },
+                }
+            }
+
+            let trimmed = input.trim();
+
+            if trimmed.is_empty() {
+                continue
+            }
+
+            if trimmed.starts_with(':') {
+                if !self.meta(trimmed) {
+                    return
+                }
+            } else {
+                self.eval(&input)
+            }
+        }
+    }
+
+    // returns false when the session should end
+    fn meta(&mut self, command: &str) -> bool {
+        let (name, rest) = match command.find(char::is_whitespace) {
+            Some(i) => (&command[.. i], &command[i ..]),
+            None    => (command, ""),
+        };
+
+        let source = format!("{}\n", rest.trim());
+
+        match name {
+            ":q" | ":quit" => return false,
+
+            ":type" => if let Some(expression) = self.expression(&source) {
+                if let Ok(t) = type_in(&source, &expression, &self.symtab, &self.typetab) {
+                    println!("{}", self.typetab.generalize(&t))
+                }
+            },
+
+            ":ast" => if let Some(expression) = self.expression(&source) {
+                println!("{:#?}", expression)
+            },
+
+            ":bytecode" => if let Some(expression) = self.expression(&source) {
+                if check_in(&source, &expression, &self.symtab, &self.typetab).is_ok() {
+                    if let Ok(compiled) = compile_in(&source, &mut self.compiler, &expression) {
+                        print!("{}", compiled)
+                    }
+                }
+            },
+
+            _ => println!("{}", format!("unknown command: {}, expected one of :type :ast :bytecode :quit", name).red()),
+        }
+
+        true
+    }
+
+    fn expression(&self, source: &str) -> Option<Expression> {
+        let mut statements = parse(source).ok()?;
+
+        match statements.pop() {
+            Some(Statement::Expression(ref e)) if statements.is_empty() => Some((**e).clone()),
+            _ => {
+                println!("{}", "expected a single expression".red());
+                None
+            },
+        }
+    }
+
+    fn eval(&mut self, source: &str) {
+        let statements = match parse(source) {
+            Ok(statements) => statements,
+            Err(_)         => return,
+        };
+
+        for statement in statements {
+            let is_expression = match statement {
+                Statement::Expression(_) => true,
+                _                        => false,
+            };
+
+            let root = Expression::Block(vec![statement]);
+
+            // a statement that fails anywhere leaves no trace, earlier ones stay
+            let names   = self.symtab.save();
+            let types   = self.typetab.save();
+            let globals = self.compiler.global_count();
+
+            let result = check_in(source, &root, &self.symtab, &self.typetab)
+                .and_then(|_| compile_in(source, &mut self.compiler, &root))
+                .and_then(|compiled| execute(source, &mut self.vm, &compiled));
+
+            match result {
+                Ok(value) => if is_expression && value != Value::Null {
+                    println!("{}", value)
+                },
+
+                Err(_) => {
+                    self.symtab.restore(names);
+                    self.typetab.restore(types);
+                    self.compiler.forget_globals(globals);
+                    self.vm.globals.truncate(globals);
+
+                    return
+                },
+            }
+        }
+    }
+}
+
+// an input continues while braces or block comments are open, an arm or definition has
+// no body yet, or the previous line was an indented block line
+fn needs_more(input: &str) -> bool {
+    if unclosed(input) {
+        return true
+    }
+
+    let last = input.lines().last().unwrap_or("");
+
+    let open = {
+        let trimmed = last.trim_end();
+        trimmed.ends_with('|') || trimmed.ends_with('=') || trimmed.ends_with(':')
+    };
+
+    open || input.lines().count() > 1 && (last.starts_with(' ') || last.starts_with('\t'))
+}
+
+// whether a brace or block comment is still open, outside of strings and line comments
+fn unclosed(input: &str) -> bool {
+    let mut depth   = 0i32;
+    let mut comment = 0i32;
+    let mut quoted  = None;
+    let mut escape  = false;
+
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let next = chars.peek().cloned();
+
+        match quoted {
+            Some(q) => if escape {
+                escape = false
+            } else if c == '\\' {
+                escape = true
+            } else if c == q {
+                quoted = None
+            },
+
+            None if comment > 0 => match (c, next) {
+                ('#', Some('[')) => { chars.next(); comment += 1 },
+                (']', Some('#')) => { chars.next(); comment -= 1 },
+                _                => (),
+            },
+
+            None => match (c, next) {
+                ('#', Some('[')) => { chars.next(); comment += 1 },
+                ('#', _)         => while chars.peek().map_or(false, |&c| c != '\n') {
+                    chars.next();
+                },
+                ('"', _) | ('\'', _) => quoted = Some(c),
+                ('{', _)             => depth += 1,
+                ('}', _)             => depth -= 1,
+                _                    => (),
+            },
+        }
+    }
+
+    depth > 0 || comment > 0
+}
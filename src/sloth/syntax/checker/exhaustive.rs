@@ -0,0 +1,379 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::*;
+
+// a pattern with its names stripped off, which is all coverage cares about
+#[derive(Debug, Clone)]
+enum Pat {
+    Wild,
+    Literal(Rc<Expression>),
+    Range(Rc<Expression>, Rc<Expression>),
+    Tuple(Vec<Pat>),
+    List(Vec<Pat>, bool),
+}
+
+#[derive(Debug, Clone)]
+enum Ctor {
+    Literal(Rc<Expression>),
+    Range(Rc<Expression>, Rc<Expression>),
+    Tuple(usize),
+    // a list length, and whether it also stands for every longer list
+    List(usize, bool),
+}
+
+enum Signature {
+    Complete(Vec<Ctor>),
+    Incomplete(Pat),
+}
+
+impl From<&Pattern> for Pat {
+    fn from(pattern: &Pattern) -> Pat {
+        match *pattern {
+            Pattern::Wildcard | Pattern::Bind(..) => Pat::Wild,
+            Pattern::Literal(ref e)               => Pat::Literal(e.clone()),
+            Pattern::Range(ref lo, ref hi)        => Pat::Range(lo.clone(), hi.clone()),
+            Pattern::Tuple(ref items)             => Pat::Tuple(items.iter().map(Pat::from).collect()),
+            Pattern::List(ref items, ref rest)    => Pat::List(items.iter().map(Pat::from).collect(), rest.is_some()),
+            Pattern::As(_, ref pattern, _)        => Pat::from(&**pattern),
+        }
+    }
+}
+
+impl Ctor {
+    fn arity(&self) -> usize {
+        match *self {
+            Ctor::Literal(_) | Ctor::Range(..) => 0,
+            Ctor::Tuple(n) | Ctor::List(n, _)  => n,
+        }
+    }
+}
+
+// warns about arms that can never be picked and about values no arm matches,
+// treating guarded arms as if their guard could always fail
+pub fn check_arms(function: &Function) -> Vec<CheckError> {
+    let arity = match function.arms.first().map(|a| &**a) {
+        Some(Expression::Arm(arm)) => arm.params.len(),
+        _                          => 0,
+    };
+
+    let mut warnings = Vec::new();
+    let mut seen     = Vec::new();
+
+    for arm in &function.arms {
+        let (row, guarded, position) = match **arm {
+            Expression::Arm(ref arm) => (arm.params.iter().map(Pat::from).collect::<Vec<Pat>>(), arm.guard.is_some(), arm.position),
+            _                        => (Vec::new(), false, function.position),
+        };
+
+        if useful(&seen, &row).is_none() {
+            warnings.push(CheckError::new_pos("unreachable arm: earlier arms match everything it does", position))
+        }
+
+        if !guarded {
+            seen.push(row)
+        }
+    }
+
+    if let Some(witness) = useful(&seen, &vec![Pat::Wild; arity]) {
+        let witness = witness.iter().map(|p| format!("{}", p)).collect::<Vec<String>>();
+        warnings.push(CheckError::new_pos(&format!("non-exhaustive arms: |{}| is not matched", witness.join(", ")), function.position))
+    }
+
+    warnings
+}
+
+// a value matched by `q` but by none of the rows, if there is one
+fn useful(rows: &[Vec<Pat>], q: &[Pat]) -> Option<Vec<Pat>> {
+    if q.is_empty() {
+        return if rows.is_empty() { Some(Vec::new()) } else { None }
+    }
+
+    let heads = rows.iter().map(|r| &r[0]).collect::<Vec<&Pat>>();
+
+    let ctors = match q[0] {
+        Pat::Wild => match signature(&heads) {
+            Signature::Complete(ctors) => ctors,
+
+            Signature::Incomplete(missing) => {
+                let defaults = rows.iter().filter(|r| is_wild(&r[0])).map(|r| r[1 ..].to_vec()).collect::<Vec<Vec<Pat>>>();
+
+                return useful(&defaults, &q[1 ..]).map(|witness| {
+                    let mut full = vec![missing];
+                    full.extend(witness);
+                    full
+                })
+            },
+        },
+
+        ref head => ctors_of(head, &heads),
+    };
+
+    for ctor in ctors {
+        let specialized = rows.iter().filter_map(|r| specialize(&ctor, r)).collect::<Vec<Vec<Pat>>>();
+
+        if let Some(q) = specialize(&ctor, q) {
+            if let Some(witness) = useful(&specialized, &q) {
+                return Some(rebuild(&ctor, witness))
+            }
+        }
+    }
+
+    None
+}
+
+fn is_wild(pat: &Pat) -> bool {
+    matches!(*pat, Pat::Wild)
+}
+
+fn specialize(ctor: &Ctor, row: &[Pat]) -> Option<Vec<Pat>> {
+    let mut fields = match (ctor, &row[0]) {
+        (_, Pat::Wild) => vec![Pat::Wild; ctor.arity()],
+
+        (Ctor::Literal(v), Pat::Literal(w)) if v.same_literal(w)    => Vec::new(),
+        (Ctor::Literal(v), Pat::Range(lo, hi)) if v.within(lo, hi) => Vec::new(),
+
+        (Ctor::Range(lo, hi), Pat::Range(l, h)) if lo.within(l, h) && hi.within(l, h) => Vec::new(),
+
+        (&Ctor::Tuple(n), Pat::Tuple(items)) if items.len() == n => items.clone(),
+
+        (&Ctor::List(k, _), Pat::List(items, false)) if items.len() == k => items.clone(),
+        (&Ctor::List(k, _), Pat::List(items, true)) if items.len() <= k  => {
+            let mut fields = items.clone();
+            fields.resize(k, Pat::Wild);
+            fields
+        },
+
+        _ => return None,
+    };
+
+    fields.extend(row[1 ..].iter().cloned());
+
+    Some(fields)
+}
+
+fn rebuild(ctor: &Ctor, mut witness: Vec<Pat>) -> Vec<Pat> {
+    let rest = witness.split_off(ctor.arity());
+
+    let head = match *ctor {
+        Ctor::Literal(ref e)        => Pat::Literal(e.clone()),
+        Ctor::Range(ref lo, ref hi) => Pat::Range(lo.clone(), hi.clone()),
+        Ctor::Tuple(_)              => Pat::Tuple(witness),
+        Ctor::List(_, open)         => Pat::List(witness, open),
+    };
+
+    let mut full = vec![head];
+    full.extend(rest);
+    full
+}
+
+// the constructors a concrete pattern in `q` has to be checked against
+fn ctors_of(head: &Pat, heads: &[&Pat]) -> Vec<Ctor> {
+    match *head {
+        Pat::Literal(ref e)          => vec![Ctor::Literal(e.clone())],
+        Pat::Range(ref lo, ref hi)   => vec![Ctor::Range(lo.clone(), hi.clone())],
+        Pat::Tuple(ref items)        => vec![Ctor::Tuple(items.len())],
+        Pat::List(ref items, false)  => vec![Ctor::List(items.len(), false)],
+        Pat::List(ref items, true)   => {
+            let longest = heads.iter().fold(items.len(), |n, h| match **h {
+                Pat::List(ref items, _) => n.max(items.len()),
+                _                       => n,
+            });
+
+            let mut ctors = (items.len() ..= longest).map(|k| Ctor::List(k, false)).collect::<Vec<Ctor>>();
+            ctors.push(Ctor::List(longest + 1, true));
+            ctors
+        },
+        Pat::Wild => unreachable!(),
+    }
+}
+
+// whether the heads of a column name every possible constructor of their type
+fn signature(heads: &[&Pat]) -> Signature {
+    let concrete = heads.iter().filter(|h| !is_wild(h)).cloned().collect::<Vec<&Pat>>();
+
+    let first = match concrete.first() {
+        Some(first) => *first,
+        None        => return Signature::Incomplete(Pat::Wild),
+    };
+
+    match *first {
+        Pat::Literal(ref e) if is_bool(e) => {
+            let has = |b: bool| concrete.iter().any(|h| match **h {
+                Pat::Literal(ref e) => match **e {
+                    Expression::Bool(value, _) => value == b,
+                    _                          => false,
+                },
+                _                   => false,
+            });
+
+            match (has(true), has(false)) {
+                (true, true) => Signature::Complete(vec![
+                    Ctor::Literal(Rc::new(Expression::Bool(true, Span::default()))),
+                    Ctor::Literal(Rc::new(Expression::Bool(false, Span::default()))),
+                ]),
+                (true, _) => Signature::Incomplete(Pat::Literal(Rc::new(Expression::Bool(false, Span::default())))),
+                _         => Signature::Incomplete(Pat::Literal(Rc::new(Expression::Bool(true, Span::default())))),
+            }
+        },
+
+        Pat::Tuple(ref items) => Signature::Complete(vec![Ctor::Tuple(items.len())]),
+
+        Pat::List(..) => {
+            let longest = concrete.iter().fold(0, |n, h| match **h {
+                Pat::List(ref items, _) => n.max(items.len()),
+                _                       => n,
+            });
+
+            let mut ctors = (0 ..= longest).map(|k| Ctor::List(k, false)).collect::<Vec<Ctor>>();
+            ctors.push(Ctor::List(longest + 1, true));
+
+            Signature::Complete(ctors)
+        },
+
+        _ => Signature::Incomplete(missing_literal(&concrete)),
+    }
+}
+
+fn is_bool(e: &Expression) -> bool {
+    matches!(*e, Expression::Bool(..))
+}
+
+// some int or char next to the ones the column covers, to show in the warning
+fn missing_literal(concrete: &[&Pat]) -> Pat {
+    let mut candidates = Vec::new();
+
+    for head in concrete {
+        let bounds = match **head {
+            Pat::Literal(ref e)        => vec![e.clone(), e.clone()],
+            Pat::Range(ref lo, ref hi) => vec![lo.clone(), hi.clone()],
+            _                          => continue,
+        };
+
+        match (&*bounds[0], &*bounds[1]) {
+            (&Expression::Int(lo, _), &Expression::Int(hi, _)) => {
+                candidates.push(Expression::Int(0, Span::default()));
+                candidates.extend(lo.checked_sub(1).map(|n| Expression::Int(n, Span::default())));
+                candidates.extend(hi.checked_add(1).map(|n| Expression::Int(n, Span::default())));
+            },
+
+            (&Expression::Char(lo, _), &Expression::Char(hi, _)) => {
+                candidates.push(Expression::Char('a', Span::default()));
+                candidates.extend((lo as u32).checked_sub(1).and_then(::std::char::from_u32).map(|c| Expression::Char(c, Span::default())));
+                candidates.extend(::std::char::from_u32(hi as u32 + 1).map(|c| Expression::Char(c, Span::default())));
+            },
+
+            _ => (),
+        }
+    }
+
+    let covered = |v: &Expression| concrete.iter().any(|h| match **h {
+        Pat::Literal(ref e)        => v.same_literal(e),
+        Pat::Range(ref lo, ref hi) => v.within(lo, hi),
+        _                          => false,
+    });
+
+    match candidates.into_iter().find(|c| !covered(c)) {
+        Some(c) => Pat::Literal(Rc::new(c)),
+        None    => Pat::Wild,
+    }
+}
+
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Pat::Wild                  => write!(f, "_"),
+            Pat::Literal(ref e)        => write_literal(f, e),
+            Pat::Range(ref lo, ref hi) => {
+                write_literal(f, lo)?;
+                write!(f, "..")?;
+                write_literal(f, hi)
+            },
+
+            Pat::Tuple(ref items) => {
+                write!(f, "(")?;
+                write_items(f, items)?;
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            },
+
+            Pat::List(ref items, open) => {
+                write!(f, "[")?;
+                write_items(f, items)?;
+                if open {
+                    write!(f, "{}..", if items.is_empty() { "" } else { ", " })?;
+                }
+                write!(f, "]")
+            },
+        }
+    }
+}
+
+fn write_items(f: &mut fmt::Formatter, items: &[Pat]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+
+    Ok(())
+}
+
+fn write_literal(f: &mut fmt::Formatter, e: &Expression) -> fmt::Result {
+    match *e {
+        Expression::Int(n, _)     => write!(f, "{}", n),
+        Expression::UInt(n, _)    => write!(f, "{}", n),
+        Expression::Float(n, _)   => write!(f, "{:?}", n),
+        Expression::Bool(b, _)    => write!(f, "{}", b),
+        Expression::Char(c, _)    => write!(f, "{:?}", c),
+        Expression::Str(ref s, _) => write!(f, "{:?}", s),
+        _                         => write!(f, "_"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::check;
+
+    fn warnings(source: &str) -> Vec<String> {
+        let (t, warnings) = check(source);
+        t.unwrap();
+
+        warnings.iter().map(|w| format!("{}", w)).collect()
+    }
+
+    #[test]
+    fn missing_char_next_to_the_lowest() {
+        assert_eq!(warnings("f := {\n  |'\0'| 1\n}\n"), vec!["non-exhaustive arms: |'a'| is not matched"]);
+        assert_eq!(warnings("f := {\n  |'\0'..'z'| 1\n}\n"), vec!["non-exhaustive arms: |'{'| is not matched"]);
+    }
+
+    #[test]
+    fn covering_arms_are_quiet() {
+        assert!(warnings("f := {\n  |true| 1\n  |false| 2\n}\n").is_empty());
+        assert!(warnings("f := {\n  |[]| 1\n  |[x, ..xs]| 2\n}\n").is_empty());
+        assert!(warnings("f := {\n  |0| 1\n  |_| 2\n}\n").is_empty());
+    }
+
+    #[test]
+    fn missing_values_are_named() {
+        assert_eq!(warnings("f := {\n  |true| 1\n}\n"), vec!["non-exhaustive arms: |false| is not matched"]);
+        assert_eq!(warnings("f := {\n  |[]| 1\n  |[x]| 2\n}\n"), vec!["non-exhaustive arms: |[_, _, ..]| is not matched"]);
+        assert_eq!(warnings("f := {\n  |(true, _)| 1\n  |(_, false)| 2\n}\n"), vec!["non-exhaustive arms: |(false, true)| is not matched"]);
+
+        // a guard can always fail
+        assert_eq!(warnings("f := {\n  |n if n > 0| 1\n}\n"), vec!["non-exhaustive arms: |_| is not matched"]);
+    }
+
+    #[test]
+    fn shadowed_arms_are_unreachable() {
+        let unreachable = "unreachable arm: earlier arms match everything it does";
+
+        assert_eq!(warnings("f := {\n  |_| 1\n  |0| 2\n}\n"), vec![unreachable]);
+        assert_eq!(warnings("f := {\n  |0..9| 1\n  |5| 2\n  |_| 3\n}\n"), vec![unreachable]);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::*;
+
+// what a type variable stands for so far
+#[derive(Debug, Clone)]
+pub enum TypeVar {
+    // still open, a numeric one may only ever become a number
+    Unbound { numeric: bool },
+    Bound(Type),
+}
+
+// type variables are shared by every scope, so they all live on the global table
+impl TypeTab {
+    fn root(&self) -> &TypeTab {
+        match self.parent {
+            Some(ref parent) => parent.root(),
+            None             => self,
+        }
+    }
+
+    fn var(&self, n: usize) -> TypeVar {
+        self.root().vars.borrow()[n].clone()
+    }
+
+    fn set_var(&self, n: usize, v: TypeVar) {
+        self.root().vars.borrow_mut()[n] = v
+    }
+
+    fn fresh_var(&self, numeric: bool) -> Type {
+        let mut vars = self.root().vars.borrow_mut();
+        vars.push(TypeVar::Unbound { numeric });
+
+        Type::Var(vars.len() - 1)
+    }
+
+    pub fn fresh(&self) -> Type {
+        self.fresh_var(false)
+    }
+
+    pub fn fresh_numeric(&self) -> Type {
+        self.fresh_var(true)
+    }
+
+    // follows bound variables at the top of a type, leaving its insides alone
+    pub fn prune(&self, t: &Type) -> Type {
+        if let Type::Var(n) = *t {
+            if let TypeVar::Bound(ref t) = self.var(n) {
+                return self.prune(t)
+            }
+        }
+
+        t.clone()
+    }
+
+    // the type with every bound variable in it replaced by what it stands for
+    pub fn resolve(&self, t: &Type) -> Type {
+        match self.prune(t) {
+            Type::Function(ref params, ref ret) => Type::Function(self.resolve_all(params), Rc::new(self.resolve(ret))),
+            Type::Tuple(ref items)              => Type::Tuple(self.resolve_all(items)),
+            Type::List(ref t)                   => Type::List(Rc::new(self.resolve(t))),
+            Type::Forall(ref vars, ref t)       => Type::Forall(vars.clone(), Rc::new(self.resolve(t))),
+            t                                   => t,
+        }
+    }
+
+    fn resolve_all(&self, types: &[Rc<Type>]) -> Vec<Rc<Type>> {
+        types.iter().map(|t| Rc::new(self.resolve(t))).collect()
+    }
+
+    // binds variables on either side until both types are the same, false if they can't be
+    pub fn unify(&self, a: &Type, b: &Type) -> bool {
+        match (self.prune(a), self.prune(b)) {
+            (Type::Any, _) | (_, Type::Any) | (Type::Undefined, _) | (_, Type::Undefined) => true,
+
+            (Type::Var(a), Type::Var(b)) if a == b => true,
+            (Type::Var(n), t) | (t, Type::Var(n))  => self.bind(n, t),
+
+            (Type::Function(ref p, ref r), Type::Function(ref q, ref s)) => p.len() == q.len() && self.unify_all(p, q) && self.unify(r, s),
+            (Type::Tuple(ref p), Type::Tuple(ref q))                     => p.len() == q.len() && self.unify_all(p, q),
+            (Type::List(ref a), Type::List(ref b))                       => self.unify(a, b),
+
+            // a number of no particular width fits any width of its kind
+            (Type::Int, ref t) | (ref t, Type::Int) if t.is_integer()  => true,
+            (Type::Float, ref t) | (ref t, Type::Float) if t.is_float() => true,
+
+            (a, b) => a == b,
+        }
+    }
+
+    fn unify_all(&self, a: &[Rc<Type>], b: &[Rc<Type>]) -> bool {
+        a.iter().zip(b.iter()).all(|(a, b)| self.unify(a, b))
+    }
+
+    fn bind(&self, n: usize, t: Type) -> bool {
+        if self.occurs(n, &t) {
+            return false
+        }
+
+        if let TypeVar::Unbound { numeric: true } = self.var(n) {
+            match t {
+                Type::Var(m)                 => self.set_var(m, TypeVar::Unbound { numeric: true }),
+                ref t if !t.is_numeric()     => return false,
+                _                            => (),
+            }
+        }
+
+        self.set_var(n, TypeVar::Bound(t));
+
+        true
+    }
+
+    fn occurs(&self, n: usize, t: &Type) -> bool {
+        let mut vars = Vec::new();
+        self.collect_vars(t, &mut vars);
+
+        vars.contains(&n)
+    }
+
+    // the open variables of a type, in order of first appearance
+    fn collect_vars(&self, t: &Type, vars: &mut Vec<usize>) {
+        match self.prune(t) {
+            Type::Var(n) if !vars.contains(&n) => vars.push(n),
+
+            Type::Function(ref params, ref ret) => {
+                for t in params {
+                    self.collect_vars(t, vars)
+                }
+                self.collect_vars(ret, vars)
+            },
+
+            Type::Tuple(ref items) => for t in items {
+                self.collect_vars(t, vars)
+            },
+
+            Type::List(ref t) => self.collect_vars(t, vars),
+
+            Type::Forall(ref bound, ref t) => {
+                let mut inner = Vec::new();
+                self.collect_vars(t, &mut inner);
+
+                for n in inner {
+                    if !bound.contains(&n) && !vars.contains(&n) {
+                        vars.push(n)
+                    }
+                }
+            },
+
+            _ => (),
+        }
+    }
+
+    // the variables some name in scope still depends on, which can't be generalised
+    fn env_vars(&self) -> Vec<usize> {
+        let mut vars = Vec::new();
+
+        for t in self.types.borrow().iter() {
+            self.collect_vars(t, &mut vars)
+        }
+
+        if let Some(ref parent) = self.parent {
+            vars.extend(parent.env_vars())
+        }
+
+        vars
+    }
+
+    // the type a definition is annotated with, leaving out what the annotation doesn't say
+    pub fn declared(&self, t: &Type, position: TokenPosition) -> CheckResult<Type> {
+        match *t {
+            Type::Mut(None)        => Ok(self.fresh()),
+            Type::Mut(Some(ref t)) => self.declared(t, position),
+
+            Type::Signature(ref params) => {
+                let mut types = Vec::new();
+
+                for t in params {
+                    types.push(Rc::new(self.declared(t, position)?))
+                }
+
+                Ok(Type::Function(types, Rc::new(self.fresh())))
+            },
+
+            Type::Identifier(ref name) => Err(CheckError::new_pos(&format!("unknown type: {}", name), position)),
+
+            ref t => Ok(t.clone()),
+        }
+    }
+
+    pub fn generalize(&self, t: &Type) -> Type {
+        let env = self.env_vars();
+
+        let mut vars = Vec::new();
+        self.collect_vars(t, &mut vars);
+        vars.retain(|n| !env.contains(n));
+
+        if vars.is_empty() {
+            self.resolve(t)
+        } else {
+            Type::Forall(vars, Rc::new(self.resolve(t)))
+        }
+    }
+
+    pub fn instantiate(&self, t: &Type) -> Type {
+        match *t {
+            Type::Forall(ref vars, ref t) => {
+                let fresh = vars.iter().map(|&n| {
+                    let numeric = match self.var(n) {
+                        TypeVar::Unbound { numeric } => numeric,
+                        TypeVar::Bound(_)            => false,
+                    };
+
+                    (n, self.fresh_var(numeric))
+                }).collect::<HashMap<usize, Type>>();
+
+                self.substitute(t, &fresh)
+            },
+
+            ref t => t.clone(),
+        }
+    }
+
+    fn substitute(&self, t: &Type, fresh: &HashMap<usize, Type>) -> Type {
+        let all = |types: &[Rc<Type>]| types.iter().map(|t| Rc::new(self.substitute(t, fresh))).collect();
+
+        match self.prune(t) {
+            Type::Var(n)                        => fresh.get(&n).cloned().unwrap_or(Type::Var(n)),
+            Type::Function(ref params, ref ret) => Type::Function(all(params), Rc::new(self.substitute(ret, fresh))),
+            Type::Tuple(ref items)              => Type::Tuple(all(items)),
+            Type::List(ref t)                   => Type::List(Rc::new(self.substitute(t, fresh))),
+            t                                   => t,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::testing::check;
+
+    fn checked(source: &str) -> String {
+        match check(source).0 {
+            Ok(t)    => format!("{}", t),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn rejection(source: &str) -> String {
+        match check(source).0 {
+            Ok(t)    => panic!("checked as {}", t),
+            Err(err) => format!("{}", err),
+        }
+    }
+
+    #[test]
+    fn variables_bind_once() {
+        let env = TypeTab::new_global();
+        let a   = env.fresh();
+
+        assert!(env.unify(&a, &Type::Bool));
+        assert!(!env.unify(&a, &Type::Char));
+        assert_eq!(env.resolve(&Type::List(Rc::new(a))), Type::List(Rc::new(Type::Bool)));
+    }
+
+    #[test]
+    fn variables_dont_occur_in_what_they_stand_for() {
+        let env = TypeTab::new_global();
+        let a   = env.fresh();
+
+        assert!(!env.unify(&a, &Type::List(Rc::new(a.clone()))));
+        assert!(env.unify(&a, &Type::List(Rc::new(Type::Int))));
+    }
+
+    #[test]
+    fn numeric_variables_only_take_numbers() {
+        let env = TypeTab::new_global();
+        let (n, m) = (env.fresh_numeric(), env.fresh_numeric());
+
+        assert!(!env.unify(&n, &Type::Str));
+        assert!(env.unify(&n, &Type::U16));
+        assert!(!env.unify(&m, &Type::Tuple(Vec::new())));
+        assert!(env.unify(&m, &Type::Float));
+    }
+
+    #[test]
+    fn unsized_numbers_fit_any_width_of_their_kind() {
+        let env = TypeTab::new_global();
+
+        assert!(env.unify(&Type::Int, &Type::U08));
+        assert!(env.unify(&Type::F32, &Type::Float));
+        assert!(!env.unify(&Type::Int, &Type::F64));
+        assert!(!env.unify(&Type::I32, &Type::I64));
+    }
+
+    #[test]
+    fn definitions_are_generalised() {
+        assert_eq!(checked("swap := {\n  |(a, b)| (b, a)\n}\nswap\n"), "forall a b. ((a, b)) -> (b, a)");
+        assert_eq!(checked("id := {\n  |x| x\n}\na := id 1\nb := id true\n(a, b)\n"), "(int, bool)");
+        assert_eq!(checked("f := {\n  |[x]| x\n  |_| 0\n}\nf\n"), "([int]) -> int");
+    }
+
+    #[test]
+    fn arguments_have_to_fit() {
+        assert_eq!(rejection("f := {\n  |x| x + 1\n}\nf true\n"), "argument 1 doesn't fit: expected int, found bool");
+        assert_eq!(rejection("f := {\n  |0| true\n  |_| 1\n}\n"), "arm returns int, but earlier arms return bool");
+    }
+}
@@ -3,12 +3,16 @@ use std::rc::Rc;
 pub mod symtab;
 pub mod typetab;
 pub mod error;
+pub mod exhaustive;
+pub mod infer;
 
 pub use super::*;
 
 pub use self::symtab::*;
 pub use self::typetab::*;
 pub use self::error::*;
+pub use self::exhaustive::*;
+pub use self::infer::*;
 
 pub type CheckResult<T> = Result<T, CheckError>;
 
@@ -22,76 +26,360 @@ pub trait Typer {
 
 impl Visitor for Expression {
     fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<()> {
+        self.get_type(sym, env).map(|_| ())
+    }
+}
+
+impl Typer for Expression {
+    fn get_type(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<Type> {
         match *self {
+            Expression::Int(..)           => Ok(Type::Int),
+            Expression::UInt(..)          => Ok(Type::U128),
+            Expression::Float(..)         => Ok(Type::Float),
+            Expression::Str(..)           => Ok(Type::Str),
+            Expression::Char(..)          => Ok(Type::Char),
+            Expression::Bool(..)          => Ok(Type::Bool),
+            Expression::Identifier(ref n, ref position, _) => match sym.get_name(&*n) {
+                Some((i, env_index)) => match env.get_type(i, env_index)? {
+                    Type::Undefined => {
+                        let t = env.fresh();
+                        env.set_type(i, env_index, t.clone())?;
+                        Ok(t)
+                    },
+
+                    t => Ok(env.instantiate(&t)),
+                },
+                None => Err(CheckError::new_pos("undeclared use", position.clone())),
+            },
+
+            // a block is worth its last expression, anything else leaves nil behind
             Expression::Block(ref statements) => {
+                let mut t = Type::Any;
+
+                // top-level definitions are declared up front, so bodies can refer to ones further
+                // down. the compiler does the same with globals, a local is only there from its definition on
+                if sym.is_global() {
+                    for s in statements {
+                        if let Statement::Definition(ref definition) = *s {
+                            definition.declare(sym, env)?
+                        }
+                    }
+                }
+
                 for s in statements {
-                    s.visit(sym, env)?
+                    t = match *s {
+                        Statement::Expression(ref e) => e.get_type(sym, env)?,
+                        ref s                        => {
+                            s.visit(sym, env)?;
+                            Type::Any
+                        },
+                    }
                 }
-                Ok(())
+
+                Ok(t)
+            },
+
+            Expression::Tuple(ref items, _) => {
+                let mut types = Vec::new();
+
+                for item in items {
+                    types.push(Rc::new(item.get_type(sym, env)?))
+                }
+
+                Ok(Type::Tuple(types))
+            },
+
+            Expression::List(ref items, _) => {
+                let element = env.fresh();
+
+                for item in items {
+                    let t = item.get_type(sym, env)?;
+
+                    if !env.unify(&element, &t) {
+                        return Err(CheckError::new(&format!("list items differ: {} and {}", env.resolve(&element), env.resolve(&t))))
+                    }
+                }
+
+                Ok(Type::List(Rc::new(element)))
             },
 
-            Expression::Identifier(ref id, ref position) => match sym.get_name(&*id) {
-                None    => Err(CheckError::new_pos("undeclared use", position.clone())),
-                Some(_) => Ok(())
+            Expression::Print(ref e, _) => {
+                e.get_type(sym, env)?;
+                Ok(Type::Any)
             },
 
-            Expression::Operation(ref operation) => operation.visit(sym, env),
-            Expression::Function(ref function)   => function.visit(sym, env),
+            Expression::Operation(ref operation) => operation.get_type(sym, env),
+            Expression::Call(ref call)           => call.get_type(sym, env),
+            Expression::Index(ref index)         => index.get_type(sym, env),
+            Expression::Convert(ref convert)     => convert.get_type(sym, env),
+            Expression::Function(ref function)   => function.get_type(sym, env),
 
-            _ => Ok(())
+            _ => Ok(Type::Any),
         }
     }
 }
 
-impl Typer for Expression {
+impl Typer for Operation {
     fn get_type(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<Type> {
-        match *self {
-            Expression::Int(_)            => Ok(Type::Int),
-            Expression::Float(_)          => Ok(Type::Float),
-            Expression::Str(_)            => Ok(Type::Str),
-            Expression::Char(_)           => Ok(Type::Char),
-            Expression::Bool(_)           => Ok(Type::Bool),
-            Expression::Identifier(ref n, ref position) => match sym.get_name(&*n) {
-                Some((i, env_index)) => Ok(env.get_type(i, env_index).unwrap()),
-                None                 => Err(CheckError::new_pos("can't get type of undeclared", position.clone())),
+        let left  = self.left.get_type(sym, env)?;
+        let right = self.right.get_type(sym, env)?;
+
+        let fits = match self.op {
+            Operand::Equal | Operand::NEqual => env.unify(&left, &right),
+            _                                => match (env.prune(&left), env.prune(&right)) {
+                (Type::Int, ref t) | (ref t, Type::Int) if t.is_float() => true,
+
+                _ => {
+                    let number = env.fresh_numeric();
+                    env.unify(&number, &left) && env.unify(&number, &right)
+                },
+            },
+        };
+
+        if !fits {
+            return Err(CheckError::new_pos(&format!("can't apply {} to {} and {}", self.op.as_str(), env.resolve(&left), env.resolve(&right)), self.position))
+        }
+
+        match self.op {
+            Operand::Equal | Operand::NEqual |
+            Operand::Lt | Operand::Gt | Operand::LtEqual | Operand::GtEqual => Ok(Type::Bool),
+
+            // mixing an int into float arithmetic gives a float, and a literal takes the width of the other side
+            _ => match (env.prune(&left), env.prune(&right)) {
+                (Type::Int, ref t) | (ref t, Type::Int) if t.is_float() => Ok(t.clone()),
+                (Type::Int, _) | (Type::Float, _)                       => Ok(right),
+                _                                                       => Ok(left),
             },
-            _ => Ok(Type::Undefined),
         }
     }
 }
 
-impl Visitor for Operation {
-    fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<()> {
-        self.left.visit(sym, env)?;
-        self.right.visit(sym, env)
+impl Typer for Call {
+    fn get_type(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<Type> {
+        let callee = self.callee.get_type(sym, env)?;
+
+        let mut args = Vec::new();
+
+        for arg in &self.args {
+            args.push(Rc::new(arg.get_type(sym, env)?))
+        }
+
+        if let Type::Function(ref params, _) = env.prune(&callee) {
+            if params.len() != args.len() {
+                return Err(CheckError::new_pos(&format!("expected {} arguments, found {}", params.len(), args.len()), self.position))
+            }
+
+            for (i, (param, arg)) in params.iter().zip(args.iter()).enumerate() {
+                if !env.unify(param, arg) {
+                    return Err(CheckError::new_pos(&format!("argument {} doesn't fit: expected {}, found {}", i + 1, env.resolve(param), env.resolve(arg)), self.position))
+                }
+
+                literal_fits(&self.args[i], &env.resolve(param), self.position)?
+            }
+        }
+
+        let ret = env.fresh();
+
+        if !env.unify(&callee, &Type::Function(args, Rc::new(ret.clone()))) {
+            return Err(CheckError::new_pos(&format!("can't call {}", env.resolve(&callee)), self.position))
+        }
+
+        Ok(ret)
     }
 }
 
-impl Visitor for Function {
-    fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<()> {
+impl Typer for Index {
+    fn get_type(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<Type> {
+        let list  = self.id.get_type(sym, env)?;
+        let index = self.index.get_type(sym, env)?;
+
+        let element = env.fresh();
+
+        if !env.unify(&list, &Type::List(Rc::new(element.clone()))) {
+            return Err(CheckError::new_pos(&format!("can't index {}", env.resolve(&list)), self.position))
+        }
+
+        if !env.unify(&index, &Type::Int) {
+            return Err(CheckError::new_pos(&format!("expected int index, found {}", env.resolve(&index)), self.position))
+        }
+
+        Ok(element)
+    }
+}
+
+impl Typer for Convert {
+    fn get_type(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<Type> {
+        let t = self.value.get_type(sym, env)?;
+
+        if !env.unify(&env.fresh_numeric(), &t) {
+            return Err(CheckError::new_pos(&format!("can't convert {} to {}", env.resolve(&t), self.t), self.position))
+        }
+
+        literal_fits(&self.value, &self.t, self.position)?;
+
+        Ok(self.t.clone())
+    }
+}
+
+// the type a pattern matches, pushing the types of the names it binds in the order `names` lists them
+fn pattern_type(pattern: &Pattern, sym: &Rc<SymTab>, env: &Rc<TypeTab>, bound: &mut Vec<Type>) -> CheckResult<Type> {
+    match *pattern {
+        Pattern::Wildcard => Ok(env.fresh()),
+
+        Pattern::Bind(..) => {
+            let t = env.fresh();
+            bound.push(t.clone());
+            Ok(t)
+        },
+
+        Pattern::Literal(ref e) => e.get_type(sym, env),
+
+        Pattern::Range(ref lo, ref hi) => {
+            let t = lo.get_type(sym, env)?;
+            let u = hi.get_type(sym, env)?;
+
+            if !env.unify(&t, &u) {
+                return Err(CheckError::new(&format!("range ends differ: {} and {}", env.resolve(&t), env.resolve(&u))))
+            }
+
+            Ok(t)
+        },
+
+        Pattern::Tuple(ref items) => {
+            let mut types = Vec::new();
+
+            for item in items {
+                types.push(Rc::new(pattern_type(item, sym, env, bound)?))
+            }
+
+            Ok(Type::Tuple(types))
+        },
+
+        Pattern::List(ref items, ref rest) => {
+            let element = env.fresh();
+
+            for item in items {
+                let t = pattern_type(item, sym, env, bound)?;
+
+                if !env.unify(&element, &t) {
+                    return Err(CheckError::new(&format!("list pattern items differ: {} and {}", env.resolve(&element), env.resolve(&t))))
+                }
+            }
+
+            let list = Type::List(Rc::new(element));
+
+            if let Some(ref rest) = *rest {
+                let t = pattern_type(rest, sym, env, bound)?;
+
+                if !env.unify(&list, &t) {
+                    return Err(CheckError::new(&format!("rest of {} can't be {}", env.resolve(&list), env.resolve(&t))))
+                }
+            }
+
+            Ok(list)
+        },
+
+        Pattern::As(_, ref pattern, _) => {
+            let slot = bound.len();
+            bound.push(Type::Undefined);
+
+            let t = pattern_type(pattern, sym, env, bound)?;
+            bound[slot] = t.clone();
+
+            Ok(t)
+        },
+    }
+}
+
+impl Typer for Function {
+    fn get_type(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<Type> {
+        let arity = match self.arms.first().map(|a| &**a) {
+            Some(&Expression::Arm(ref arm)) => arm.params.len(),
+            _                               => 0,
+        };
+
+        let params = (0 .. arity).map(|_| Rc::new(env.fresh())).collect::<Vec<Rc<Type>>>();
+        let ret    = env.fresh();
+
         for arm in &self.arms {
             let mut param_names = Vec::new();
-            
+            let mut param_types = Vec::new();
+
             match **arm {
                 Expression::Arm(ref arm) => {
-                    for p in &arm.params {
-                        match **p {
-                            Expression::Identifier(ref i, _) => param_names.push(i.clone()),
-                            _ => (),
+                    if arm.params.len() != arity {
+                        return Err(CheckError::new_pos(&format!("expected {} parameters, found {}", arity, arm.params.len()), arm.position))
+                    }
+
+                    for (i, p) in arm.params.iter().enumerate() {
+                        for (name, position) in p.names() {
+                            if param_names.contains(&name) {
+                                return Err(CheckError::new_pos(&format!("bound more than once: {}", name), position))
+                            }
+
+                            param_names.push(name)
+                        }
+
+                        let t = pattern_type(p, sym, env, &mut param_types)?;
+
+                        if !env.unify(&params[i], &t) {
+                            return Err(CheckError::new_pos(&format!("parameter {} takes {} in earlier arms, found {}", i + 1, env.resolve(&params[i]), env.resolve(&t)), arm.position))
                         }
                     }
 
                     let local_sym = Rc::new(SymTab::new(sym.clone(), param_names.as_slice()));
-                    let local_env = Rc::new(TypeTab::new(env.clone(), &arm.params.iter().map(|_| Type::Any).collect()));
+                    let local_env = Rc::new(TypeTab::new(env.clone(), &param_types));
+
+                    if let Some(ref guard) = arm.guard {
+                        let t = guard.get_type(&local_sym, &local_env)?;
+
+                        if !env.unify(&t, &Type::Bool) {
+                            return Err(CheckError::new_pos(&format!("expected bool guard, found {}", env.resolve(&t)), arm.position))
+                        }
+                    }
+
+                    let t = arm.body.get_type(&local_sym, &local_env)?;
 
-                    arm.body.visit(&local_sym, &local_env)?;
+                    if !env.unify(&ret, &t) {
+                        return Err(CheckError::new_pos(&format!("arm returns {}, but earlier arms return {}", env.resolve(&t), env.resolve(&ret)), arm.position))
+                    }
                 },
-                
+
                 ref c => c.visit(sym, env)?
             }
         }
 
+        for warning in check_arms(self) {
+            sym.warn(warning)
+        }
+
+        Ok(Type::Function(params, Rc::new(ret)))
+    }
+}
+
+// a number literal has to be in range of the width it ends up as
+fn literal_fits(e: &Expression, t: &Type, position: TokenPosition) -> CheckResult<()> {
+    let fits = match (e, t) {
+        (&Expression::Int(n, _), &Type::I08)  => n >= i8::min_value() as i128 && n <= i8::max_value() as i128,
+        (&Expression::Int(n, _), &Type::I16)  => n >= i16::min_value() as i128 && n <= i16::max_value() as i128,
+        (&Expression::Int(n, _), &Type::I32)  => n >= i32::min_value() as i128 && n <= i32::max_value() as i128,
+        (&Expression::Int(n, _), &Type::I64)  => n >= i64::min_value() as i128 && n <= i64::max_value() as i128,
+        (&Expression::Int(n, _), &Type::U08)  => n >= 0 && n <= u8::max_value() as i128,
+        (&Expression::Int(n, _), &Type::U16)  => n >= 0 && n <= u16::max_value() as i128,
+        (&Expression::Int(n, _), &Type::U32)  => n >= 0 && n <= u32::max_value() as i128,
+        (&Expression::Int(n, _), &Type::U64)  => n >= 0 && n <= u64::max_value() as i128,
+        (&Expression::Int(n, _), &Type::U128) => n >= 0,
+        (&Expression::UInt(..), _)            => *t == Type::U128,
+
+        (&Expression::Float(n, _), &Type::F32) => n.is_nan() || n.abs() <= f32::MAX as f64,
+
+        _ => true,
+    };
+
+    if fits {
         Ok(())
+    } else {
+        Err(CheckError::new_pos(&format!("literal out of range for {}", t), position))
     }
 }
 
@@ -100,27 +388,192 @@ impl Visitor for Statement {
         match *self {
             Statement::Expression(ref e) => e.visit(sym, env),
             Statement::Definition(ref b) => b.visit(sym, env),
-            _ => Ok(())
+            Statement::Assignment(ref a) => a.visit(sym, env),
         }
     }
 }
 
-impl Visitor for Definition {
-    fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<()> {
+impl Definition {
+    // puts the name in scope with its declared type, or one to be inferred, ahead of its
+    // definition, so functions can recurse and refer to each other
+    pub fn declare(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<()> {
         match *self.name {
-            Expression::Identifier(ref name, _) => {
+            Expression::Identifier(ref name, ..) => {
+                if let Some((_, 0)) = sym.get_name(name) {
+                    let scope = if sym.is_global() { "global" } else { "local" };
+                    return Err(CheckError::new_pos(&format!("redeclared {}: {}", scope, name), self.position))
+                }
+
                 let index = sym.add_name(name);
                 if index >= env.size() {
                     env.grow();
                 }
 
-                match self.right {
-                    Some(ref right) => right.visit(&sym, &env),
-                    None => Ok(()),
+                let t = match self.t {
+                    Some(ref declared) => env.declared(declared, self.position)?,
+                    None               => env.fresh(),
+                };
+
+                env.set_type(index, 0, t)
+            }
+
+            _ => Err(CheckError::new_pos("unexpected declaration", self.position)),
+        }
+    }
+}
+
+impl Visitor for Definition {
+    fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<()> {
+        match *self.name {
+            Expression::Identifier(ref name, ..) => {
+                // a global was declared with the rest of its block
+                let index = match sym.get_name(name) {
+                    Some((index, 0)) if sym.is_global() => index,
+                    _                                   => {
+                        self.declare(sym, env)?;
+                        sym.add_name(name)
+                    },
+                };
+
+                let t = env.get_type(index, 0)?;
+
+                if let Some(ref right) = self.right {
+                    let found = right.get_type(sym, env)?;
+
+                    if !env.unify(&t, &found) {
+                        return Err(match self.t {
+                            Some(_) => CheckError::new_pos(&format!("{} is declared {}, but defined as {}", name, env.resolve(&t), env.resolve(&found)), self.position),
+                            None    => CheckError::new_pos(&format!("{} is used as {}, but defined as {}", name, env.resolve(&t), env.resolve(&found)), self.position),
+                        })
+                    }
+
+                    literal_fits(right, &env.resolve(&t), self.position)?;
+
+                    // only functions are generalised, a name bound to any other value keeps one type
+                    if let Expression::Function(_) = **right {
+                        env.set_type(index, 0, Type::Undefined)?;
+
+                        let scheme = env.generalize(&t);
+                        env.set_type(index, 0, scheme)?;
+                    }
                 }
+
+                Ok(())
             }
 
             _ => Err(CheckError::new_pos("unexpected declaration", self.position)),
         }
     }
 }
+
+impl Visitor for Assignment {
+    fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> CheckResult<()> {
+        let left  = self.left.get_type(sym, env)?;
+        let right = self.right.get_type(sym, env)?;
+
+        if !env.unify(&left, &right) {
+            return Err(CheckError::new_pos(&format!("can't assign {} to {}", env.resolve(&right), env.resolve(&left)), self.position))
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::testing::{check, run};
+
+    // the message and line of the error the source is turned down with
+    fn rejection(source: &str) -> (String, usize) {
+        match check(source).0 {
+            Ok(t)    => panic!("checked as {}", t),
+            Err(err) => (format!("{}", err), err.position.map_or(0, |p| p.line)),
+        }
+    }
+
+    #[test]
+    fn globals_can_be_used_before_their_definition() {
+        let source = "is_even := {
+  |0| true
+  |n| is_odd n - 1
+}
+
+is_odd := {
+  |0| false
+  |n| is_even n - 1
+}
+
+is_even 10
+";
+        assert_eq!(check(source).0.unwrap(), Type::Bool);
+        assert_eq!(run(source).unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn locals_can_not_be_used_before_their_definition() {
+        let source = "f := {
+  |n|
+    a := b
+    b := n
+    a
+}
+";
+        assert_eq!(rejection(source), (String::from("undeclared use"), 3));
+    }
+
+    #[test]
+    fn locals_used_before_their_definition_are_the_globals() {
+        let source = "b := 100
+
+f := {
+  |n|
+    a := b
+    b := n
+    a
+}
+
+f 1
+";
+        assert_eq!(check(source).0.unwrap(), Type::Int);
+        assert_eq!(run(source).unwrap(), Value::Int(100));
+    }
+
+    #[test]
+    fn rejects_redeclared_global() {
+        assert_eq!(rejection("f := 1\nf := 2\n"), (String::from("redeclared global: f"), 2));
+    }
+
+    #[test]
+    fn rejects_redeclared_local() {
+        let source = "f := {
+  |n|
+    a := 1
+    a := n
+    a
+}
+";
+        assert_eq!(rejection(source), (String::from("redeclared local: a"), 4));
+    }
+
+    #[test]
+    fn definitions_keep_to_their_annotations() {
+        assert_eq!(rejection("c: bool = 1\n"), (String::from("c is declared bool, but defined as int"), 1));
+        assert_eq!(rejection("a: u08 = 255\nb: i32 = a\n"), (String::from("b is declared i32, but defined as u08"), 2));
+        assert_eq!(rejection("b: i32 = 1.5\n"), (String::from("b is declared i32, but defined as float"), 1));
+    }
+
+    #[test]
+    fn literals_take_the_width_they_meet() {
+        assert_eq!(check("a: u08 = 1\na + 2\n").0.unwrap(), Type::U08);
+        assert_eq!(check("f: (i08) = {\n  |n| n\n}\nf\n").0.unwrap().to_string(), "(i08) -> i08");
+
+        assert_eq!(rejection("a: u08 = 256\n"), (String::from("literal out of range for u08"), 1));
+        assert_eq!(rejection("f: (i08) = {\n  |n| n\n}\nf 300\n"), (String::from("literal out of range for i08"), 4));
+    }
+
+    #[test]
+    fn widths_dont_mix() {
+        assert_eq!(rejection("a: u08 = 1\nb: i32 = 2\na + b\n"), (String::from("can't apply + to u08 and i32"), 3));
+    }
+}
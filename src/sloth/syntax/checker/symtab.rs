@@ -4,9 +4,12 @@ use std::collections::HashMap;
 
 use std::fmt;
 
+use super::CheckError;
+
 pub struct SymTab {
-    parent: Option<Rc<SymTab>>,
-    names:  RefCell<HashMap<String, usize>>,
+    parent:   Option<Rc<SymTab>>,
+    names:    RefCell<HashMap<String, usize>>,
+    warnings: RefCell<Vec<CheckError>>,
 }
 
 impl SymTab {
@@ -18,15 +21,37 @@ impl SymTab {
         }
 
         SymTab {
-            parent: Some(parent),
-            names:  RefCell::new(hash_names),
+            parent:   Some(parent),
+            names:    RefCell::new(hash_names),
+            warnings: RefCell::new(Vec::new()),
         }
     }
 
     pub fn new_global() -> SymTab {
         SymTab {
-            parent: None,
-            names:  RefCell::new(HashMap::new()),
+            parent:   None,
+            names:    RefCell::new(HashMap::new()),
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    // definitions in the global scope are globals, every other scope's are locals
+    pub fn is_global(&self) -> bool {
+        self.parent.is_none()
+    }
+
+    // warnings are collected on the global table, whichever scope raises them
+    pub fn warn(&self, warning: CheckError) {
+        match self.parent {
+            Some(ref parent) => parent.warn(warning),
+            None             => self.warnings.borrow_mut().push(warning),
+        }
+    }
+
+    pub fn take_warnings(&self) -> Vec<CheckError> {
+        match self.parent {
+            Some(ref parent) => parent.take_warnings(),
+            None             => self.warnings.borrow_mut().drain(..).collect(),
         }
     }
 
@@ -41,6 +66,15 @@ impl SymTab {
         new_index
     }
 
+    // the names declared so far, for `restore` to go back to when what declared more fails
+    pub fn save(&self) -> HashMap<String, usize> {
+        self.names.borrow().clone()
+    }
+
+    pub fn restore(&self, names: HashMap<String, usize>) {
+        *self.names.borrow_mut() = names
+    }
+
     pub fn get_name(&self, name: &str) -> Option<(usize, usize)> {
         self.get_name_internal(name, 0)
     }
@@ -5,8 +5,9 @@ use std::fmt;
 use super::*;
 
 pub struct TypeTab {
-    parent: Option<Rc<TypeTab>>,
-    types: RefCell<Vec<Type>>,
+    pub(super) parent: Option<Rc<TypeTab>>,
+    pub(super) types:  RefCell<Vec<Type>>,
+    pub(super) vars:   RefCell<Vec<TypeVar>>,
 }
 
 impl TypeTab {
@@ -14,6 +15,7 @@ impl TypeTab {
         TypeTab {
             parent: Some(parent),
             types: RefCell::new(types.clone()),
+            vars:  RefCell::new(Vec::new()),
         }
     }
 
@@ -21,6 +23,7 @@ impl TypeTab {
         TypeTab {
             parent: None,
             types: RefCell::new(Vec::new()),
+            vars:  RefCell::new(Vec::new()),
         }
     }
 
@@ -33,6 +36,7 @@ impl TypeTab {
         TypeTab {
             parent: Some(parent),
             types: RefCell::new(stack),
+            vars:  RefCell::new(Vec::new()),
         }
     }
 
@@ -77,7 +81,7 @@ impl TypeTab {
         }
 
         for (i, v) in self.types.borrow().iter().enumerate() {
-            println!("({} : {}) = {:?}", i, env_index, v)
+            println!("({} : {}) = {}", i, env_index, self.resolve(v))
         }
     }
 
@@ -90,12 +94,22 @@ impl TypeTab {
         }
 
         for (i, v) in self.types.borrow().iter().enumerate() {
-            try!(writeln!(f, "({} : {}) = {:?}", i, env_index, v))
+            try!(writeln!(f, "({} : {}) = {}", i, env_index, self.resolve(v)))
         }
 
         Ok(())
     }
 
+    // the types and type variables as they are now, for `restore` to go back to
+    pub fn save(&self) -> (Vec<Type>, Vec<TypeVar>) {
+        (self.types.borrow().clone(), self.vars.borrow().clone())
+    }
+
+    pub fn restore(&self, (types, vars): (Vec<Type>, Vec<TypeVar>)) {
+        *self.types.borrow_mut() = types;
+        *self.vars.borrow_mut()  = vars
+    }
+
     pub fn size(&self) -> usize {
         self.types.borrow().len()
     }
@@ -0,0 +1,662 @@
+use std::char;
+use std::rc::Rc;
+
+use super::*;
+
+// a .slothc file is the magic and version followed by the outermost block, every number
+// little endian:
+//
+//   block    name (flag, string) | locals (u32, strings) | consts (u32, values)
+//            | code (u32, opcodes) | lines (u32, pc line col) | arms (u32, start end)
+//            | upvalues (u32, name local index) | globals (u32, strings)
+//   string   u32 length, utf-8
+//   value    tag, payload: numbers at their width, strings, blocks and items nested
+//   opcode   tag, operands at their width
+pub const MAGIC:   &[u8; 4] = b"SLTH";
+pub const VERSION: u16      = 5;
+
+// how deep functions can nest in a file before it's taken to be malformed
+const MAX_DEPTH: usize = 256;
+
+const VALUE_NULL:     u8 = 0;
+const VALUE_BOOL:     u8 = 1;
+const VALUE_INT:      u8 = 2;
+const VALUE_FLOAT:    u8 = 3;
+const VALUE_CHAR:     u8 = 4;
+const VALUE_I08:      u8 = 5;
+const VALUE_I16:      u8 = 6;
+const VALUE_I32:      u8 = 7;
+const VALUE_I64:      u8 = 8;
+const VALUE_I128:     u8 = 9;
+const VALUE_U08:      u8 = 10;
+const VALUE_U16:      u8 = 11;
+const VALUE_U32:      u8 = 12;
+const VALUE_U64:      u8 = 13;
+const VALUE_U128:     u8 = 14;
+const VALUE_F32:      u8 = 15;
+const VALUE_F64:      u8 = 16;
+const VALUE_STR:      u8 = 17;
+const VALUE_FUNCTION: u8 = 18;
+const VALUE_TUPLE:    u8 = 19;
+const VALUE_LIST:     u8 = 20;
+
+const WIDTHS: [Width; 12] = [
+    Width::I08, Width::I16, Width::I32, Width::I64, Width::I128,
+    Width::U08, Width::U16, Width::U32, Width::U64, Width::U128,
+    Width::F32, Width::F64,
+];
+
+impl CompiledBlock {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        write_block(&mut out, self);
+
+        out
+    }
+}
+
+fn write_u32(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u32).to_le_bytes())
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_u32(out, s.len());
+    out.extend_from_slice(s.as_bytes())
+}
+
+fn write_block(out: &mut Vec<u8>, block: &CompiledBlock) {
+    match block.name {
+        Some(ref name) => {
+            out.push(1);
+            write_str(out, name)
+        },
+        None => out.push(0),
+    }
+
+    write_u32(out, block.locals.len());
+
+    for local in block.locals.iter() {
+        write_str(out, local)
+    }
+
+    write_u32(out, block.consts.len());
+
+    for value in block.consts.iter() {
+        write_value(out, *value)
+    }
+
+    write_u32(out, block.code.len());
+
+    for op in block.code.iter() {
+        write_op(out, *op)
+    }
+
+    write_u32(out, block.lines.entries().len());
+
+    for &(pc, position) in block.lines.entries() {
+        write_u32(out, pc as usize);
+        write_u32(out, position.line);
+        write_u32(out, position.col);
+    }
+
+    write_u32(out, block.arms.len());
+
+    for &(start, end) in block.arms.iter() {
+        write_u32(out, start as usize);
+        write_u32(out, end as usize);
+    }
+
+    write_u32(out, block.upvalues.len());
+
+    for capture in block.upvalues.iter() {
+        write_str(out, &capture.name);
+        out.push(capture.local as u8);
+        out.extend_from_slice(&capture.index.to_le_bytes())
+    }
+
+    write_u32(out, block.globals.len());
+
+    for global in block.globals.iter() {
+        write_str(out, global)
+    }
+}
+
+fn write_value(out: &mut Vec<u8>, value: Value) {
+    match value {
+        Value::Null     => out.push(VALUE_NULL),
+        Value::Bool(b)  => out.extend_from_slice(&[VALUE_BOOL, b as u8]),
+        Value::Int(n)   => { out.push(VALUE_INT);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::Float(n) => { out.push(VALUE_FLOAT); out.extend_from_slice(&n.to_bits().to_le_bytes()) },
+        Value::Char(c)  => { out.push(VALUE_CHAR);  out.extend_from_slice(&(c as u32).to_le_bytes()) },
+        Value::I08(n)   => { out.push(VALUE_I08);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::I16(n)   => { out.push(VALUE_I16);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::I32(n)   => { out.push(VALUE_I32);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::I64(n)   => { out.push(VALUE_I64);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::I128(n)  => { out.push(VALUE_I128);  out.extend_from_slice(&n.to_le_bytes()) },
+        Value::U08(n)   => { out.push(VALUE_U08);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::U16(n)   => { out.push(VALUE_U16);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::U32(n)   => { out.push(VALUE_U32);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::U64(n)   => { out.push(VALUE_U64);   out.extend_from_slice(&n.to_le_bytes()) },
+        Value::U128(n)  => { out.push(VALUE_U128);  out.extend_from_slice(&n.to_le_bytes()) },
+        Value::F32(n)   => { out.push(VALUE_F32);   out.extend_from_slice(&n.to_bits().to_le_bytes()) },
+        Value::F64(n)   => { out.push(VALUE_F64);   out.extend_from_slice(&n.to_bits().to_le_bytes()) },
+
+        Value::HeapObject(p) => match unsafe { &(*p).kind } {
+            HeapKind::Str(ref s) => {
+                out.push(VALUE_STR);
+                write_str(out, s)
+            },
+
+            HeapKind::Function(ref block) => {
+                out.push(VALUE_FUNCTION);
+                write_block(out, block)
+            },
+
+            HeapKind::Tuple(ref items) => {
+                out.push(VALUE_TUPLE);
+                write_items(out, items)
+            },
+
+            HeapKind::List(ref items) => {
+                out.push(VALUE_LIST);
+                write_items(out, items)
+            },
+
+            HeapKind::Closure(..) | HeapKind::Upvalue(_) => unreachable!("closures only exist while running"),
+        },
+    }
+}
+
+fn write_items(out: &mut Vec<u8>, items: &[Value]) {
+    write_u32(out, items.len());
+
+    for item in items {
+        write_value(out, *item)
+    }
+}
+
+fn write_op(out: &mut Vec<u8>, op: OpCode) {
+    let u16s = |out: &mut Vec<u8>, tag: u8, n: u16| {
+        out.push(tag);
+        out.extend_from_slice(&n.to_le_bytes())
+    };
+
+    let i16s = |out: &mut Vec<u8>, tag: u8, n: i16| {
+        out.push(tag);
+        out.extend_from_slice(&n.to_le_bytes())
+    };
+
+    match op {
+        OpCode::Add   => out.push(0),
+        OpCode::Sub   => out.push(1),
+        OpCode::Mul   => out.push(2),
+        OpCode::Rem   => out.push(3),
+        OpCode::Div   => out.push(4),
+        OpCode::Pow   => out.push(5),
+        OpCode::Neg   => out.push(6),
+        OpCode::Convert(width) => out.extend_from_slice(&[7, WIDTHS.iter().position(|&w| w == width).unwrap() as u8]),
+        OpCode::Lt    => out.push(8),
+        OpCode::Gt    => out.push(9),
+        OpCode::LtEq  => out.push(10),
+        OpCode::GtEq  => out.push(11),
+        OpCode::Eq    => out.push(12),
+        OpCode::NotEq => out.push(13),
+
+        OpCode::LoadConst(i)   => u16s(out, 14, i),
+        OpCode::LoadLocal(i)   => u16s(out, 15, i),
+        OpCode::StoreLocal(i)  => u16s(out, 16, i),
+        OpCode::MakeTuple(n)   => u16s(out, 17, n),
+        OpCode::MakeList(n)    => u16s(out, 18, n),
+        OpCode::GetItem(i)     => u16s(out, 19, i),
+        OpCode::ListRest(i)    => u16s(out, 20, i),
+        OpCode::TestTuple(n)   => u16s(out, 21, n),
+        OpCode::TestListLen(n) => u16s(out, 22, n),
+        OpCode::TestListMin(n) => u16s(out, 23, n),
+
+        OpCode::TestRange(lo, hi) => {
+            u16s(out, 24, lo);
+            out.extend_from_slice(&hi.to_le_bytes())
+        },
+
+        OpCode::BranchTrue(d)  => i16s(out, 25, d),
+        OpCode::BranchFalse(d) => i16s(out, 26, d),
+        OpCode::Jump(d)        => i16s(out, 27, d),
+
+        OpCode::Pop          => out.push(28),
+        OpCode::Return       => out.push(29),
+        OpCode::Call(n)      => out.extend_from_slice(&[30, n]),
+        OpCode::MatchFail(n) => out.extend_from_slice(&[31, n]),
+        OpCode::Print        => out.push(32),
+
+        OpCode::MakeClosure(i)  => u16s(out, 33, i),
+        OpCode::LoadUpvalue(i)  => u16s(out, 34, i),
+        OpCode::StoreUpvalue(i) => u16s(out, 35, i),
+        OpCode::LoadGlobal(i)   => u16s(out, 36, i),
+        OpCode::StoreGlobal(i)  => u16s(out, 37, i),
+        OpCode::TailCall(n)     => out.extend_from_slice(&[38, n]),
+        OpCode::Index           => out.push(39),
+    }
+}
+
+// reads a .slothc file back, putting its strings and functions on the machine's heap,
+// what it gives back has to pass `verify` before it can run
+pub fn load_bytecode(vm: &mut VirtualMachine, bytes: &[u8]) -> LoadResult<CompiledBlock> {
+    let mut reader = Reader { bytes, at: 0 };
+
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(LoadError::new("not a sloth bytecode file", 0))
+    }
+
+    let version = reader.u16()?;
+
+    if version != VERSION {
+        return Err(LoadError::new(&format!("unsupported bytecode version {}, expected {}", version, VERSION), MAGIC.len()))
+    }
+
+    let block = reader.block(vm, 0)?;
+
+    if reader.at != bytes.len() {
+        return Err(reader.error("trailing bytes after the outermost block"))
+    }
+
+    Ok(block)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at:    usize,
+}
+
+macro_rules! read_le {
+    ($reader:expr, $t:ty) => {{
+        let mut buffer = [0; ::std::mem::size_of::<$t>()];
+        buffer.copy_from_slice($reader.take(::std::mem::size_of::<$t>())?);
+
+        <$t>::from_le_bytes(buffer)
+    }}
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, message: &str) -> LoadError {
+        LoadError::new(message, self.at)
+    }
+
+    fn take(&mut self, n: usize) -> LoadResult<&'a [u8]> {
+        if self.bytes.len() - self.at < n {
+            return Err(self.error("unexpected end of file"))
+        }
+
+        let taken = &self.bytes[self.at .. self.at + n];
+        self.at  += n;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> LoadResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> LoadResult<u16> {
+        Ok(read_le!(self, u16))
+    }
+
+    fn i16(&mut self) -> LoadResult<i16> {
+        Ok(read_le!(self, i16))
+    }
+
+    fn u32(&mut self) -> LoadResult<u32> {
+        Ok(read_le!(self, u32))
+    }
+
+    fn count(&mut self) -> LoadResult<usize> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn string(&mut self) -> LoadResult<String> {
+        let n     = self.count()?;
+        let start = self.at;
+
+        String::from_utf8(self.take(n)?.to_vec()).map_err(|_| LoadError::new("string isn't utf-8", start))
+    }
+
+    fn block(&mut self, vm: &mut VirtualMachine, depth: usize) -> LoadResult<CompiledBlock> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(&format!("functions nest deeper than {}", MAX_DEPTH)))
+        }
+
+        let name = match self.u8()? {
+            0 => None,
+            1 => Some(Rc::new(self.string()?)),
+            _ => return Err(self.error("malformed function name")),
+        };
+
+        let mut locals = Vec::new();
+
+        for _ in 0 .. self.count()? {
+            locals.push(self.string()?)
+        }
+
+        let mut consts = Vec::new();
+
+        for _ in 0 .. self.count()? {
+            consts.push(self.value(vm, depth)?)
+        }
+
+        let mut code = Vec::new();
+
+        for _ in 0 .. self.count()? {
+            code.push(self.op()?)
+        }
+
+        let mut lines = LineTable::new();
+        let mut last  = None;
+
+        for _ in 0 .. self.count()? {
+            let pc       = self.u32()?;
+            let position = TokenPosition { line: self.count()?, col: self.count()? };
+
+            if last.map_or(false, |last| pc <= last) || pc as usize >= code.len() {
+                return Err(self.error("line table out of order"))
+            }
+
+            lines.record(pc as usize, position);
+            last = Some(pc)
+        }
+
+        let mut arms = Vec::new();
+
+        for _ in 0 .. self.count()? {
+            let (start, end) = (self.u32()?, self.u32()?);
+
+            if start > end || end as usize > code.len() {
+                return Err(self.error("arm outside of its function"))
+            }
+
+            arms.push((start, end))
+        }
+
+        let mut upvalues = Vec::new();
+
+        for _ in 0 .. self.count()? {
+            let name  = self.string()?;
+
+            let local = match self.u8()? {
+                0 => false,
+                1 => true,
+                _ => return Err(self.error("malformed upvalue")),
+            };
+
+            upvalues.push(Capture { name, local, index: self.u16()? })
+        }
+
+        let mut globals = Vec::new();
+
+        for _ in 0 .. self.count()? {
+            globals.push(self.string()?)
+        }
+
+        Ok(CompiledBlock {
+            code:   code.into_boxed_slice(),
+            consts: consts.into_boxed_slice(),
+            locals: locals.into_boxed_slice(),
+            lines,
+            name,
+            arms:   arms.into_boxed_slice(),
+            upvalues: upvalues.into_boxed_slice(),
+            globals:  globals.into_boxed_slice(),
+            verified: false,
+        })
+    }
+
+    fn value(&mut self, vm: &mut VirtualMachine, depth: usize) -> LoadResult<Value> {
+        let at = self.at;
+
+        let value = match self.u8()? {
+            VALUE_NULL  => Value::Null,
+            VALUE_BOOL  => match self.u8()? {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                _ => return Err(LoadError::new("malformed bool", at)),
+            },
+            VALUE_INT   => Value::Int(read_le!(self, i128)),
+            VALUE_FLOAT => Value::Float(f64::from_bits(read_le!(self, u64))),
+            VALUE_CHAR  => match char::from_u32(self.u32()?) {
+                Some(c) => Value::Char(c),
+                None    => return Err(LoadError::new("malformed char", at)),
+            },
+            VALUE_I08   => Value::I08(read_le!(self, i8)),
+            VALUE_I16   => Value::I16(read_le!(self, i16)),
+            VALUE_I32   => Value::I32(read_le!(self, i32)),
+            VALUE_I64   => Value::I64(read_le!(self, i64)),
+            VALUE_I128  => Value::I128(read_le!(self, i128)),
+            VALUE_U08   => Value::U08(read_le!(self, u8)),
+            VALUE_U16   => Value::U16(read_le!(self, u16)),
+            VALUE_U32   => Value::U32(read_le!(self, u32)),
+            VALUE_U64   => Value::U64(read_le!(self, u64)),
+            VALUE_U128  => Value::U128(read_le!(self, u128)),
+            VALUE_F32   => Value::F32(f32::from_bits(read_le!(self, u32))),
+            VALUE_F64   => Value::F64(f64::from_bits(read_le!(self, u64))),
+
+            VALUE_STR => {
+                let s = self.string()?;
+                vm.allocate_object(HeapKind::Str(Rc::new(s)))
+            },
+
+            VALUE_FUNCTION => {
+                let block = self.block(vm, depth + 1)?;
+                vm.allocate_object(HeapKind::Function(block))
+            },
+
+            tag @ VALUE_TUPLE | tag @ VALUE_LIST => {
+                if depth > MAX_DEPTH {
+                    return Err(LoadError::new(&format!("values nest deeper than {}", MAX_DEPTH), at))
+                }
+
+                let mut items = Vec::new();
+
+                for _ in 0 .. self.count()? {
+                    items.push(self.value(vm, depth + 1)?)
+                }
+
+                if tag == VALUE_TUPLE {
+                    vm.allocate_object(HeapKind::Tuple(items))
+                } else {
+                    vm.allocate_object(HeapKind::List(items))
+                }
+            },
+
+            tag => return Err(LoadError::new(&format!("unknown constant tag {}", tag), at)),
+        };
+
+        Ok(value)
+    }
+
+    fn op(&mut self) -> LoadResult<OpCode> {
+        let at = self.at;
+
+        let op = match self.u8()? {
+            0  => OpCode::Add,
+            1  => OpCode::Sub,
+            2  => OpCode::Mul,
+            3  => OpCode::Rem,
+            4  => OpCode::Div,
+            5  => OpCode::Pow,
+            6  => OpCode::Neg,
+            7  => match WIDTHS.get(self.u8()? as usize) {
+                Some(&width) => OpCode::Convert(width),
+                None         => return Err(LoadError::new("unknown width", at)),
+            },
+            8  => OpCode::Lt,
+            9  => OpCode::Gt,
+            10 => OpCode::LtEq,
+            11 => OpCode::GtEq,
+            12 => OpCode::Eq,
+            13 => OpCode::NotEq,
+            14 => OpCode::LoadConst(self.u16()?),
+            15 => OpCode::LoadLocal(self.u16()?),
+            16 => OpCode::StoreLocal(self.u16()?),
+            17 => OpCode::MakeTuple(self.u16()?),
+            18 => OpCode::MakeList(self.u16()?),
+            19 => OpCode::GetItem(self.u16()?),
+            20 => OpCode::ListRest(self.u16()?),
+            21 => OpCode::TestTuple(self.u16()?),
+            22 => OpCode::TestListLen(self.u16()?),
+            23 => OpCode::TestListMin(self.u16()?),
+            24 => OpCode::TestRange(self.u16()?, self.u16()?),
+            25 => OpCode::BranchTrue(self.i16()?),
+            26 => OpCode::BranchFalse(self.i16()?),
+            27 => OpCode::Jump(self.i16()?),
+            28 => OpCode::Pop,
+            29 => OpCode::Return,
+            30 => OpCode::Call(self.u8()?),
+            31 => OpCode::MatchFail(self.u8()?),
+            32 => OpCode::Print,
+            33 => OpCode::MakeClosure(self.u16()?),
+            34 => OpCode::LoadUpvalue(self.u16()?),
+            35 => OpCode::StoreUpvalue(self.u16()?),
+            36 => OpCode::LoadGlobal(self.u16()?),
+            37 => OpCode::StoreGlobal(self.u16()?),
+            38 => OpCode::TailCall(self.u8()?),
+            39 => OpCode::Index,
+            tag => return Err(LoadError::new(&format!("unknown opcode {}", tag), at)),
+        };
+
+        Ok(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "double := {
+  |n| n * 2
+}
+
+pick := {
+  |[head, ..tail]| (head, tail)
+  |[]|             (0, [])
+}
+
+answer := double 21
+list   := [1, 2, 3]
+split  := pick list
+
+(answer, split, \"sloth\", 'c', 2.5, 255u08, 340282366920938463463374607431768211455u128)
+";
+
+    fn compile(vm: &mut VirtualMachine, source: &str) -> CompiledBlock {
+        let tokens = Layout::new(lexer(0, &mut source.chars())).collect::<Result<Vec<Token>, _>>().unwrap();
+        let root   = Expression::Block(Parser::new(Traveler::new(tokens)).parse().unwrap());
+
+        Compiler::new(vm.clone()).compile(&root).unwrap()
+    }
+
+    fn load_error(bytes: &[u8]) -> (String, usize) {
+        match load_bytecode(&mut VirtualMachine::new(), bytes) {
+            Ok(block) => panic!("loaded {:?}", block.code),
+            Err(err)  => {
+                let LoadErrorValue::Constant(ref message) = err.value;
+                (message.clone(), err.offset)
+            },
+        }
+    }
+
+    // the header, an unnamed block without locals or constants, and its one instruction
+    const RETURN_AT: usize = 4 + 2 + 1 + 4 + 4 + 4;
+
+    fn returning() -> Vec<u8> {
+        let block = CompiledBlock {
+            code:   Box::new([OpCode::Return]),
+            consts: Box::new([]),
+            locals: Box::new([]),
+            lines:  LineTable::new(),
+            name:   None,
+            arms:   Box::new([]),
+            upvalues: Box::new([]),
+            globals:  Box::new([]),
+            verified: false,
+        };
+
+        block.to_bytes()
+    }
+
+    #[test]
+    fn round_trip_runs_the_same() {
+        let mut vm = VirtualMachine::new();
+        let compiled = compile(&mut vm, SOURCE);
+        let expected = vm.execute(&compiled).unwrap();
+
+        let mut loaded_vm = VirtualMachine::new();
+        let mut loaded    = load_bytecode(&mut loaded_vm, &compiled.to_bytes()).unwrap();
+
+        assert!(!loaded.verified);
+        verify(&mut loaded).unwrap();
+
+        assert_eq!(loaded.to_bytes(), compiled.to_bytes());
+
+        let result = loaded_vm.execute(&loaded).unwrap();
+
+        assert_eq!(format!("{}", expected), "(42, (1, [2, 3]), sloth, c, 2.5, 255, 340282366920938463463374607431768211455)");
+        assert_eq!(format!("{}", result), format!("{}", expected));
+    }
+
+    #[test]
+    fn loading_leaves_verifying() {
+        let mut vm    = VirtualMachine::new();
+        let mut block = load_bytecode(&mut vm, &returning()).unwrap();
+
+        assert!(verify(&mut block).is_err());
+        assert!(vm.execute(&block).is_err());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(load_error(b"#!/bin/sh\n"), (String::from("not a sloth bytecode file"), 0));
+        assert_eq!(load_error(b""), (String::from("unexpected end of file"), 0));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = returning();
+        bytes[MAGIC.len() .. MAGIC.len() + 2].copy_from_slice(&(VERSION + 1).to_le_bytes());
+
+        assert_eq!(load_error(&bytes), (format!("unsupported bytecode version {}, expected {}", VERSION + 1, VERSION), MAGIC.len()));
+    }
+
+    #[test]
+    fn rejects_every_truncation() {
+        let mut vm = VirtualMachine::new();
+        let bytes  = compile(&mut vm, SOURCE).to_bytes();
+
+        for end in MAGIC.len() + 2 .. bytes.len() {
+            let (message, offset) = load_error(&bytes[.. end]);
+
+            assert_eq!(message, "unexpected end of file", "cut at {}", end);
+            assert!(offset <= end, "cut at {}, reported at {}", end, offset);
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = returning();
+        let end       = bytes.len();
+        bytes.push(0);
+
+        assert_eq!(load_error(&bytes), (String::from("trailing bytes after the outermost block"), end));
+    }
+
+    #[test]
+    fn rejects_unknown_opcode() {
+        let mut bytes = returning();
+        assert_eq!(bytes[RETURN_AT], 29);
+
+        bytes[RETURN_AT] = 0xff;
+
+        assert_eq!(load_error(&bytes), (String::from("unknown opcode 255"), RETURN_AT));
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
 use std::mem;
 use std::rc::Rc;
 use std::collections::hash_map::Entry;
@@ -16,31 +17,115 @@ pub struct CompiledBlock {
     pub code:   Box<[OpCode]>,
     pub consts: Box<[Value]>,
     pub locals: Box<[String]>,
+    pub lines:  LineTable,
+    // the function's name and the pcs each arm's body spans, a block outside any function has neither
+    pub name:   Option<Rc<String>>,
+    pub arms:   Box<[(u32, u32)]>,
+    // what a closure over the block captures, filled in by the enclosing compiler
+    pub upvalues: Box<[Capture]>,
+    // the names of the globals slots, only the outermost block has any
+    pub globals:  Box<[String]>,
+    // the machine only runs verified blocks, the compiler verifies what it emits like the
+    // loader does what it reads
+    pub verified: bool,
+}
+
+// a captured variable, either a local of the enclosing function or one of its own upvalues
+#[derive(Debug, Clone)]
+pub struct Capture {
+    pub name:  String,
+    pub local: bool,
+    pub index: u16,
+}
+
+impl CompiledBlock {
+    // the arm whose body `pc` lies in, counting from 1 like the source does
+    pub fn arm_at(&self, pc: usize) -> Option<usize> {
+        let pc = pc as u32;
+
+        self.arms.iter().position(|&(start, end)| start <= pc && pc < end).map(|i| i + 1)
+    }
+}
+
+// top-level definitions, shared by the compiler of the outermost block and every one nested in it
+#[derive(Default)]
+struct Globals {
+    names:  Vec<String>,
+    widths: HashMap<u16, Width>,
 }
 
 pub struct Compiler {
     locals: HashMap<String, u16>,
+    widths: HashMap<u16, Width>,
+    names:  Vec<String>,
     code:   Vec<OpCode>,
     consts: Vec<Value>,
     vm:     VirtualMachine,
+    // the position emitted instructions are attributed to
+    position: Option<TokenPosition>,
+    lines:    LineTable,
+    arms:     Vec<(u32, u32)>,
+    name:     Option<Rc<String>>,
+    // names the enclosing functions can see, and the ones of them this function captures
+    outer:    HashSet<String>,
+    upvalues: Vec<String>,
+    globals:  Rc<RefCell<Globals>>,
+    // definitions are globals in the outermost block and locals anywhere else
+    toplevel: bool,
 }
 
 impl Compiler {
     pub fn new(vm: VirtualMachine) -> Compiler {
         Compiler {
             locals: HashMap::new(),
+            widths: HashMap::new(),
+            names:  Vec::new(),
             code:   Vec::new(),
             consts: Vec::new(),
             vm,
+            position: None,
+            lines:    LineTable::new(),
+            arms:     Vec::new(),
+            name:     None,
+            outer:    HashSet::new(),
+            upvalues: Vec::new(),
+            globals:  Rc::new(RefCell::new(Globals::default())),
+            toplevel: true,
         }
     }
 
+    // declared globals are kept between calls, so later blocks can refer to earlier ones,
+    // unless the block declaring them failed to compile
     pub fn compile(&mut self, block: &Expression) -> CompileResult<CompiledBlock> {
-        self.compile_main(block)
+        self.code.clear();
+        self.consts.clear();
+        self.lines.clear();
+        self.arms.clear();
+
+        let declared = self.global_count();
+        let result   = self.compile_main(block);
+
+        if result.is_err() {
+            self.forget_globals(declared)
+        }
+
+        result
+    }
+
+    pub fn global_count(&self) -> usize {
+        self.globals.borrow().names.len()
+    }
+
+    // drops the globals declared after the first `count`, for blocks that didn't get to define them
+    pub fn forget_globals(&mut self, count: usize) {
+        let mut globals = self.globals.borrow_mut();
+
+        globals.names.truncate(count);
+        globals.widths.retain(|&i, _| (i as usize) < count);
     }
     
     fn declare_local(&mut self, name: &str) -> CompileResult<u16> {
-        let i = self.locals.len();
+        let i = self.names.len();
         
         if i > (u16::max_value() as usize) {
             return Err(CompileError::new(&format!("local overflow: {}", u16::max_value())))
@@ -53,6 +138,7 @@ impl Compiler {
             Entry::Occupied(_) => Err(CompileError::new(&format!("redeclared local: {}", name))),
             Entry::Vacant(v)   => {
                 v.insert(i);
+                self.names.push(name.to_string());
                 Ok(i)
             }
         }
@@ -61,25 +147,120 @@ impl Compiler {
     fn fetch_local(&mut self, name: &str) -> CompileResult<u16> {
         self.locals.get(name).map(|i| *i).ok_or(CompileError::new(&format!("undeclared local: {}", name)))
     }
+
+    fn declare_global(&mut self, name: &str, width: Option<Width>, position: TokenPosition) -> CompileResult<u16> {
+        let mut globals = self.globals.borrow_mut();
+        let i = globals.names.len();
+
+        if i > (u16::max_value() as usize) {
+            return Err(CompileError::new_pos(&format!("global overflow: {}", u16::max_value()), position))
+        }
+
+        if globals.names.iter().any(|n| n == name) {
+            return Err(CompileError::new_pos(&format!("redeclared global: {}", name), position))
+        }
+
+        let i = i as u16;
+
+        globals.names.push(name.to_string());
+
+        if let Some(width) = width {
+            globals.widths.insert(i, width);
+        }
+
+        Ok(i)
+    }
+
+    fn fetch_global(&self, name: &str) -> Option<u16> {
+        self.globals.borrow().names.iter().position(|n| n == name).map(|i| i as u16)
+    }
+
+    // every top-level definition is declared before any of them is compiled, so their
+    // bodies can refer to definitions further down
+    fn declare_globals(&mut self, block: &Expression) -> CompileResult<()> {
+        if let Expression::Block(ref statements) = *block {
+            for s in statements {
+                if let Statement::Definition(ref def) = *s {
+                    if let Expression::Identifier(ref id, position, _) = *def.name {
+                        self.declare_global(id, def.t.as_ref().and_then(Width::from_type), position)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // the upvalue a name from an enclosing function is captured as, capturing it the first time
+    fn fetch_upvalue(&mut self, name: &str) -> CompileResult<u16> {
+        if let Some(i) = self.upvalues.iter().position(|n| n == name) {
+            return Ok(i as u16)
+        }
+
+        let i = self.upvalues.len();
+
+        if i > (u16::max_value() as usize) {
+            return Err(CompileError::new(&format!("upvalue overflow: {}", u16::max_value())))
+        }
+
+        self.upvalues.push(name.to_string());
+
+        Ok(i as u16)
+    }
+
+    // how a function nested in this one reaches a name it captures
+    fn capture(&mut self, name: &str) -> CompileResult<Capture> {
+        let (local, index) = match self.locals.get(name) {
+            Some(&i) => (true, i),
+            None     => (false, self.fetch_upvalue(name)?),
+        };
+
+        Ok(Capture { name: name.to_string(), local, index })
+    }
+
+    // a name is a local, then one captured from an enclosing function, then a global
+    fn emit_load(&mut self, name: &str) -> CompileResult<()> {
+        if self.locals.contains_key(name) {
+            let i = self.fetch_local(name)?;
+            self.emit(OpCode::LoadLocal(i));
+        } else if self.outer.contains(name) {
+            let i = self.fetch_upvalue(name)?;
+            self.emit(OpCode::LoadUpvalue(i));
+        } else {
+            match self.fetch_global(name) {
+                Some(i) => self.emit(OpCode::LoadGlobal(i)),
+                None    => return Err(CompileError::new(&format!("undeclared variable: {}", name))),
+            }
+        }
+
+        Ok(())
+    }
     
     fn emit(&mut self, op: OpCode) {
+        if let Some(position) = self.position {
+            self.lines.record(self.code.len(), position)
+        }
+
         self.code.push(op)
     }
     
-    fn emit_load_const(&mut self, value: Value) -> CompileResult<()> {
+    fn make_const(&mut self, value: Value) -> CompileResult<u16> {
         let i = self.consts.len();
 
         if i > (u16::max_value() as usize) {
             Err(CompileError::new(&format!("constant overflow: {}", u16::max_value())))
         } else {
-            let i = i as u16;
-            
             self.consts.push(value);
-            self.emit(OpCode::LoadConst(i));
-
-            Ok(())
+            Ok(i as u16)
         }
     }
+
+    fn emit_load_const(&mut self, value: Value) -> CompileResult<()> {
+        let i = self.make_const(value)?;
+        self.emit(OpCode::LoadConst(i));
+
+        Ok(())
+    }
     
     fn emit_branch_false(&mut self) -> JumpPatch {
         let result = JumpPatch(self.code.len());
@@ -127,32 +308,95 @@ impl Compiler {
     fn compile_statement(&mut self, s: &Statement) -> CompileResult<()> {
         match *s {
             Statement::Definition(ref def) => {                
-                let id = match *def.name {
-                    Expression::Identifier(ref i, _) => i,
-                    _                                => unreachable!(),
+                let (id, position) = match *def.name {
+                    Expression::Identifier(ref i, position, _) => (i, position),
+                    _                                       => unreachable!(),
                 };
 
-                match def.right {
+                let width = def.t.as_ref().and_then(Width::from_type);
+
+                // globals were declared before anything in the block was compiled
+                let global = if self.toplevel { self.fetch_global(id) } else { None };
+
+                let i = match def.right {
                     Some(ref e) => {
-                        let i = self.declare_local(id)?;
+                        let i = match global {
+                            Some(i) => i,
+                            None    => self.declare_local(id)?,
+                        };
+
+                        match (&def.t, &**e) {
+                            (&Some(Type::Signature(ref params)), &Expression::Function(ref function)) => {
+                                let widths = params.iter().map(|t| Width::from_type(t)).collect::<Vec<Option<Width>>>();
+                                self.emit_function(function, &widths, Some(id.clone()))?
+                            },
+
+                            (_, &Expression::Function(ref function)) => self.emit_function(function, &[], Some(id.clone()))?,
+
+                            _ => self.compile_expression(&e)?,
+                        }
 
-                        self.compile_expression(&e)?;
-                        self.emit(OpCode::StoreLocal(i))
+                        if let Some(width) = width {
+                            self.position = Some(position);
+                            self.emit(OpCode::Convert(width))
+                        }
+
+                        self.emit(if global.is_some() { OpCode::StoreGlobal(i) } else { OpCode::StoreLocal(i) });
+                        i
                     },
-                    None    => { self.declare_local(id)?; },
+                    None    => match global {
+                        Some(i) => i,
+                        None    => self.declare_local(id)?,
+                    },
+                };
+
+                if let (Some(width), None) = (width, global) {
+                    self.widths.insert(i, width);
                 }
 
                 Ok(())
             },
 
             Statement::Assignment(ref ass) => {
-                let id = match *ass.left {
-                    Expression::Identifier(ref i, _) => i,
-                    _                                => unreachable!(),
+                let (id, position) = match *ass.left {
+                    Expression::Identifier(ref i, position, _) => (i, position),
+                    _                                       => unreachable!(),
                 };
 
                 self.compile_expression(&ass.right)?;
+
+                if !self.locals.contains_key(&**id) && self.outer.contains(&**id) {
+                    let i = self.fetch_upvalue(id)?;
+
+                    self.position = Some(position);
+                    self.emit(OpCode::StoreUpvalue(i));
+
+                    return Ok(())
+                }
+
+                if !self.locals.contains_key(&**id) {
+                    if let Some(i) = self.fetch_global(id) {
+                        let width = self.globals.borrow().widths.get(&i).cloned();
+
+                        self.position = Some(position);
+
+                        if let Some(width) = width {
+                            self.emit(OpCode::Convert(width))
+                        }
+
+                        self.emit(OpCode::StoreGlobal(i));
+
+                        return Ok(())
+                    }
+                }
+
                 let i = self.fetch_local(id)?;
+
+                if let Some(&width) = self.widths.get(&i) {
+                    self.position = Some(position);
+                    self.emit(OpCode::Convert(width))
+                }
+
                 self.emit(OpCode::StoreLocal(i));
 
                 Ok(())
@@ -169,30 +413,40 @@ impl Compiler {
 
     fn compile_expression(&mut self, e: &Expression) -> CompileResult<()> {
         match *e {
-            Expression::Int(ref n)   => self.emit_load_const(Value::Int(*n)),
-            Expression::Float(ref n) => self.emit_load_const(Value::Float(*n)),
-            Expression::Bool(ref n)  => self.emit_load_const(Value::Bool(*n)),
-            Expression::Char(ref n)  => self.emit_load_const(Value::Char(*n)),
-            Expression::Str(ref n)   => {
+            Expression::Int(ref n, _)   => self.emit_load_const(Value::Int(*n)),
+            Expression::UInt(ref n, _)  => self.emit_load_const(Value::U128(*n)),
+            Expression::Float(ref n, _) => self.emit_load_const(Value::Float(*n)),
+            Expression::Bool(ref n, _)  => self.emit_load_const(Value::Bool(*n)),
+            Expression::Char(ref n, _)  => self.emit_load_const(Value::Char(*n)),
+            Expression::Str(ref n, _)   => {
                 let value = self.vm.allocate_object(HeapKind::Str((*n).clone()));
                 self.emit_load_const(value)
             },
 
-            Expression::Identifier(ref id, _) => {
-                let i = self.fetch_local(id)?;
-
-                self.emit(OpCode::LoadLocal(i));
-                Ok(())
+            Expression::Identifier(ref id, position, _) => {
+                self.position = Some(position);
+                self.emit_load(id)
             },
 
             Expression::Block(ref statements) => {
-                for s in statements {
-                    self.compile_statement(s)?;
+                if let Some((last, statements)) = statements.split_last() {
+                    for s in statements {
+                        self.compile_statement(s)?;
+                    }
+
+                    match *last {
+                        Statement::Expression(ref e) => self.compile_expression(e),
+                        ref s                        => {
+                            self.compile_statement(s)?;
+                            self.emit_load_const(Value::Null)
+                        },
+                    }
+                } else {
+                    self.emit_load_const(Value::Null)
                 }
-                Ok(())
             },
             
-            Expression::Print(ref e) => {
+            Expression::Print(ref e, _) => {
                 self.compile_expression(e)?;
                 self.emit(OpCode::Print);
                 self.emit_load_const(Value::Null)?;
@@ -200,61 +454,45 @@ impl Compiler {
                 Ok(())
             }
 
-            Expression::Arm(ref arm) => {
-                let cond = Expression::Operation(
-                    Operation {
-                        left:     Rc::new(Expression::Int(0)),
-                        right:    Rc::new(Expression::Int(0)),
-                        op:       Operand::Equal,
-                        position: arm.position.clone(),
-                    }
-                );
-
-                self.compile_expression(&cond)?;
+            Expression::Function(ref function) => self.emit_function(function, &[], None),
 
-                let if_not = self.emit_branch_false();
-
-                let arm_block: CompiledBlock = {
-                    let mut locals = HashMap::<String, u16>::new();
-
-                    for (arg, i) in self.locals.clone() {
-                        locals.insert(arg, i);
-                    }
-
-                    let mut i = 0u16;
-                    for p in arm.params.iter() {
-                        match **p {
-                            Expression::Identifier(ref id, _) => {
-                                locals.insert((**id).clone(), i);
-                            },
+            Expression::Convert(ref convert) => {
+                let width = match Width::from_type(&convert.t) {
+                    Some(width) => width,
+                    None        => return Err(CompileError::new_pos(&format!("can't convert to {}", convert.t), convert.position)),
+                };
 
-                            _ => {
-                                locals.insert("".to_string(), i);
-                            },
-                        }
+                // a suffixed literal is a constant of its width, `255u08` is never an int
+                match *convert.value {
+                    Expression::Int(..) | Expression::UInt(..) | Expression::Float(..) => match literal_value(&convert.value).convert(width) {
+                        Ok(value) => self.emit_load_const(value),
+                        Err(err)  => Err(CompileError::new_pos(&format!("{}", err), convert.position)),
+                    },
 
-                        i += 1
-                    }
+                    _ => {
+                        self.compile_expression(&convert.value)?;
 
-                    let mut compiler = Compiler {
-                        vm:     self.vm.clone(),
-                        locals,
-                        code:   Vec::new(),
-                        consts: Vec::new(),
-                    };
+                        self.position = Some(convert.position);
+                        self.emit(OpCode::Convert(width));
+                        Ok(())
+                    },
+                }
+            },
 
-                    compiler.compile_main(&arm.body)?
-                };
+            Expression::Arm(ref arm) => Err(CompileError::new_pos("arm outside of function", arm.position)),
 
-                let arm_func = self.vm.allocate_object(HeapKind::Function(arm_block));
-                self.emit_load_const(arm_func)?;
+            Expression::Tuple(ref items, _) | Expression::List(ref items, _) => {
+                if items.len() > (u16::max_value() as usize) {
+                    return Err(CompileError::new(&format!("item overflow: {}", u16::max_value())))
+                }
 
-                self.patch_jump(if_not)
-            },
+                for item in items.iter() {
+                    self.compile_expression(item)?
+                }
 
-            Expression::Function(ref function) => {
-                for arm in function.arms.iter() {
-                    self.compile_expression(arm)?
+                match *e {
+                    Expression::Tuple(..) => self.emit(OpCode::MakeTuple(items.len() as u16)),
+                    _                     => self.emit(OpCode::MakeList(items.len() as u16)),
                 }
 
                 Ok(())
@@ -264,6 +502,8 @@ impl Compiler {
                 self.compile_expression(&operation.left)?;
                 self.compile_expression(&operation.right)?;
 
+                self.position = Some(operation.position);
+
                 match operation.op {
                     Operand::Add     => self.emit(OpCode::Add),
                     Operand::Sub     => self.emit(OpCode::Sub),
@@ -274,7 +514,7 @@ impl Compiler {
 
                     Operand::Lt      => self.emit(OpCode::Lt),
                     Operand::Gt      => self.emit(OpCode::Gt),
-                    Operand::LtEqual => self.emit(OpCode::GtEq),
+                    Operand::LtEqual => self.emit(OpCode::LtEq),
                     Operand::GtEqual => self.emit(OpCode::GtEq),
                     Operand::Equal   => self.emit(OpCode::Eq),
                     Operand::NEqual  => self.emit(OpCode::NotEq),
@@ -284,38 +524,330 @@ impl Compiler {
             },
             
             Expression::Call(ref call) => {
-                self.vm.value_stack.push(Value::Int(call.args.len() as i64)); // grr hack
-
                 self.compile_expression(&call.callee)?;
 
                 for arg in call.args.iter() {
                     self.compile_expression(&arg)?
                 }
 
+                // a call is reported where its callee is named
+                self.position = match *call.callee {
+                    Expression::Identifier(_, position, _) => Some(position),
+                    _                                   => Some(call.position),
+                };
+
                 self.emit(OpCode::Call(call.args.len() as u8));
 
                 Ok(())
             },
 
-            _ => Ok(()),
+            Expression::Index(ref index) => {
+                self.compile_expression(&index.id)?;
+                self.compile_expression(&index.index)?;
+
+                self.position = Some(index.position);
+                self.emit(OpCode::Index);
+
+                Ok(())
+            },
+
+            Expression::EOF => Err(CompileError::new("unexpected end of source")),
+        }
+    }
+
+    // `name` is what the function was defined as, for stack traces
+    fn emit_function(&mut self, function: &Function, widths: &[Option<Width>], name: Option<Rc<String>>) -> CompileResult<()> {
+        let (mut block, upvalues) = self.compile_function(function, widths, name)?;
+
+        let mut captures = Vec::new();
+
+        for name in upvalues.iter() {
+            captures.push(self.capture(name)?)
+        }
+
+        block.upvalues = captures.into_boxed_slice();
+
+        let closes = !block.upvalues.is_empty();
+        let value  = self.vm.allocate_object(HeapKind::Function(block));
+
+        // a function that captures nothing is the same function every time
+        if closes {
+            let i = self.make_const(value)?;
+            self.emit(OpCode::MakeClosure(i));
+
+            Ok(())
+        } else {
+            self.emit_load_const(value)
+        }
+    }
+
+    // all arms share one block: the arguments sit in the first locals, a decision tree
+    // over them picks the arm and binds its names, then jumps to the arm's body. names from
+    // enclosing functions come back as the upvalues the block needs
+    fn compile_function(&mut self, function: &Function, widths: &[Option<Width>], name: Option<Rc<String>>) -> CompileResult<(CompiledBlock, Vec<String>)> {
+        let arity = match function.arms.first().map(|a| &**a) {
+            Some(&Expression::Arm(ref arm)) => arm.params.len(),
+            _                               => 0,
+        };
+
+        if arity > (u8::max_value() as usize) {
+            return Err(CompileError::new_pos(&format!("parameter overflow: {}", u8::max_value()), function.position))
+        }
+
+        let mut outer = self.outer.clone();
+        outer.extend(self.locals.keys().cloned());
+
+        let mut compiler = Compiler {
+            vm:     self.vm.clone(),
+            locals: HashMap::new(),
+            widths: HashMap::new(),
+            names:  vec![String::new(); arity],
+            code:   Vec::new(),
+            consts: Vec::new(),
+            position: Some(function.position),
+            lines:    LineTable::new(),
+            arms:     Vec::new(),
+            name,
+            outer,
+            upvalues: Vec::new(),
+            globals:  self.globals.clone(),
+            toplevel: false,
+        };
+
+        let mut scopes = Vec::new();
+
+        for arm in function.arms.iter() {
+            let mut scope = HashMap::new();
+
+            match **arm {
+                Expression::Arm(ref arm) => {
+                    if arm.params.len() != arity {
+                        return Err(CompileError::new_pos(&format!("expected {} parameters, found {}", arity, arm.params.len()), arm.position))
+                    }
+
+                    for param in arm.params.iter() {
+                        for (name, _) in param.names() {
+                            let slot = compiler.names.len() as u16;
+
+                            compiler.names.push((*name).clone());
+                            scope.insert((*name).clone(), slot);
+                        }
+                    }
+                },
+
+                _ => if arity != 0 {
+                    return Err(CompileError::new_pos(&format!("expected arm with {} parameters", arity), function.position))
+                },
+            }
+
+            scopes.push(scope)
+        }
+
+        if compiler.names.len() > (u16::max_value() as usize) {
+            return Err(CompileError::new(&format!("local overflow: {}", u16::max_value())))
+        }
+
+        // arguments take the widths the signature declares before any arm looks at them
+        for (i, width) in widths.iter().enumerate().take(arity) {
+            if let Some(width) = *width {
+                compiler.emit(OpCode::LoadLocal(i as u16));
+                compiler.emit(OpCode::Convert(width));
+                compiler.emit(OpCode::StoreLocal(i as u16));
+            }
+        }
+
+        let mut entries = vec![Vec::new(); function.arms.len()];
+
+        compiler.compile_decision(&decision_tree(&function.arms), arity as u8, &function.arms, &scopes, &mut entries)?;
+
+        for (i, arm) in function.arms.iter().enumerate() {
+            for entry in entries[i].drain(..) {
+                compiler.patch_jump(entry)?
+            }
+
+            let outer = compiler.enter_scope(&scopes[i]);
+            let start = compiler.code.len() as u32;
+
+            match **arm {
+                Expression::Arm(ref arm) => {
+                    compiler.position = Some(arm.position);
+                    compiler.compile_body(&arm.body)?
+                },
+                ref body                 => compiler.compile_body(body)?,
+            }
+
+            compiler.arms.push((start, compiler.code.len() as u32));
+            compiler.locals = outer
+        }
+
+        let upvalues = mem::take(&mut compiler.upvalues);
+
+        Ok((compiler.finish(), upvalues))
+    }
+
+    // an arm's body is what the function returns, so a call there takes over the frame
+    // instead of coming back to it just to return
+    fn compile_body(&mut self, body: &Expression) -> CompileResult<()> {
+        self.compile_expression(body)?;
+
+        match (ends_in_call(body), self.code.last().cloned()) {
+            (true, Some(OpCode::Call(args))) => {
+                let last = self.code.len() - 1;
+                self.code[last] = OpCode::TailCall(args)
+            },
+
+            _ => self.emit(OpCode::Return),
+        }
+
+        Ok(())
+    }
+
+    fn compile_decision(&mut self, decision: &Decision, arity: u8, arms: &[Rc<Expression>], scopes: &[HashMap<String, u16>], entries: &mut [Vec<JumpPatch>]) -> CompileResult<()> {
+        match *decision {
+            Decision::Fail => {
+                self.emit(OpCode::MatchFail(arity));
+                Ok(())
+            },
+
+            Decision::Test { ref access, ref test, ref success, ref failure } => {
+                self.emit_access(access);
+
+                match *test {
+                    Test::Literal(ref literal) => {
+                        self.compile_expression(literal)?;
+                        self.emit(OpCode::Eq)
+                    },
+
+                    Test::Range(ref lo, ref hi) => {
+                        let lo = self.make_const(literal_value(lo))?;
+                        let hi = self.make_const(literal_value(hi))?;
+
+                        self.emit(OpCode::TestRange(lo, hi))
+                    },
+
+                    Test::Tuple(n)   => self.emit(OpCode::TestTuple(n as u16)),
+                    Test::ListLen(n) => self.emit(OpCode::TestListLen(n as u16)),
+                    Test::ListMin(n) => self.emit(OpCode::TestListMin(n as u16)),
+                }
+
+                let fail = self.emit_branch_false();
+
+                self.compile_decision(success, arity, arms, scopes, entries)?;
+                self.patch_jump(fail)?;
+                self.compile_decision(failure, arity, arms, scopes, entries)
+            },
+
+            Decision::Leaf { arm, ref bindings, ref otherwise } => {
+                for &(ref name, ref access) in bindings.iter() {
+                    self.emit_access(access);
+                    self.emit(OpCode::StoreLocal(scopes[arm][&**name]))
+                }
+
+                match *otherwise {
+                    Some(ref otherwise) => {
+                        let (guard, guard_position) = match *arms[arm] {
+                            Expression::Arm(ref arm) => (arm.guard.clone().unwrap(), arm.position),
+                            _                        => unreachable!(),
+                        };
+
+                        let outer = self.enter_scope(&scopes[arm]);
+                        self.compile_expression(&guard)?;
+                        self.position = Some(guard_position);
+                        self.locals = outer;
+
+                        let fail = self.emit_branch_false();
+
+                        entries[arm].push(self.emit_jump());
+
+                        self.patch_jump(fail)?;
+                        self.compile_decision(otherwise, arity, arms, scopes, entries)
+                    },
+
+                    None => {
+                        entries[arm].push(self.emit_jump());
+                        Ok(())
+                    },
+                }
+            },
         }
     }
 
+    fn emit_access(&mut self, access: &Access) {
+        match *access {
+            Access::Arg(i)          => self.emit(OpCode::LoadLocal(i)),
+            Access::Item(ref of, i) => {
+                self.emit_access(of);
+                self.emit(OpCode::GetItem(i))
+            },
+            Access::Rest(ref of, i) => {
+                self.emit_access(of);
+                self.emit(OpCode::ListRest(i))
+            },
+        }
+    }
+
+    // returns the scope to restore afterwards
+    fn enter_scope(&mut self, scope: &HashMap<String, u16>) -> HashMap<String, u16> {
+        let outer = self.locals.clone();
+
+        for (name, slot) in scope.iter() {
+            self.locals.insert(name.clone(), *slot);
+        }
+
+        outer
+    }
+
     fn compile_main(&mut self, block: &Expression) -> CompileResult<CompiledBlock> {
+        self.declare_globals(block)?;
         self.compile_expression(block)?;
-        self.emit_load_const(Value::Null)?;
-        self.code.push(OpCode::Return);
-        
-        let mut locals = vec![String::new(); self.locals.len()];
-        
-        for (name, i) in self.locals.drain() {
-            locals[i as usize] = name
+        self.emit(OpCode::Return);
+
+        let mut compiled = self.finish();
+        compiled.globals = self.globals.borrow().names.clone().into_boxed_slice();
+
+        // a block the verifier turns down is a bug in the compiler, better caught here than
+        // by the machine running it
+        if let Err(err) = verify(&mut compiled) {
+            return Err(CompileError::new(&format!("emitted invalid bytecode: {}", err)))
         }
-        
-        Ok(CompiledBlock {
-            code: mem::replace(&mut self.code, Vec::new()).into_boxed_slice(),
-            consts: mem::replace(&mut self.consts, Vec::new()).into_boxed_slice(),
-            locals: locals.into_boxed_slice(),
-        })
+
+        Ok(compiled)
+    }
+
+    fn finish(&mut self) -> CompiledBlock {
+        CompiledBlock {
+            code:   mem::take(&mut self.code).into_boxed_slice(),
+            consts: mem::take(&mut self.consts).into_boxed_slice(),
+            locals: self.names.clone().into_boxed_slice(),
+            lines:  mem::take(&mut self.lines),
+            name:   self.name.clone(),
+            arms:   mem::take(&mut self.arms).into_boxed_slice(),
+            upvalues: Box::new([]),
+            globals:  Box::new([]),
+            verified: false,
+        }
+    }
+}
+
+// whether the last thing an expression does is a call, a block's being its last statement's
+fn ends_in_call(e: &Expression) -> bool {
+    match *e {
+        Expression::Call(_)               => true,
+        Expression::Block(ref statements) => match statements.last() {
+            Some(&Statement::Expression(ref e)) => ends_in_call(e),
+            _                                   => false,
+        },
+        _                                 => false,
+    }
+}
+
+fn literal_value(e: &Expression) -> Value {
+    match *e {
+        Expression::Int(n, _)   => Value::Int(n),
+        Expression::UInt(n, _)  => Value::U128(n),
+        Expression::Float(n, _) => Value::Float(n),
+        Expression::Bool(n, _)  => Value::Bool(n),
+        Expression::Char(n, _)  => Value::Char(n),
+        _                       => Value::Null,
     }
 }
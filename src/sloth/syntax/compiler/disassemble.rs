@@ -0,0 +1,177 @@
+use std::fmt;
+
+use super::*;
+
+impl OpCode {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            OpCode::Add             => "add",
+            OpCode::Sub             => "sub",
+            OpCode::Mul             => "mul",
+            OpCode::Rem             => "rem",
+            OpCode::Div             => "div",
+            OpCode::Pow             => "pow",
+            OpCode::Neg             => "neg",
+            OpCode::Convert(_)      => "convert",
+            OpCode::Lt              => "lt",
+            OpCode::Gt              => "gt",
+            OpCode::LtEq            => "lt_eq",
+            OpCode::GtEq            => "gt_eq",
+            OpCode::Eq              => "eq",
+            OpCode::NotEq           => "not_eq",
+            OpCode::LoadConst(_)    => "load_const",
+            OpCode::LoadLocal(_)    => "load_local",
+            OpCode::StoreLocal(_)   => "store_local",
+            OpCode::MakeTuple(_)    => "make_tuple",
+            OpCode::MakeList(_)     => "make_list",
+            OpCode::GetItem(_)      => "get_item",
+            OpCode::ListRest(_)     => "list_rest",
+            OpCode::Index           => "index",
+            OpCode::TestTuple(_)    => "test_tuple",
+            OpCode::TestListLen(_)  => "test_list_len",
+            OpCode::TestListMin(_)  => "test_list_min",
+            OpCode::TestRange(..)   => "test_range",
+            OpCode::BranchTrue(_)   => "branch_true",
+            OpCode::BranchFalse(_)  => "branch_false",
+            OpCode::Jump(_)         => "jump",
+            OpCode::Pop             => "pop",
+            OpCode::Return          => "return",
+            OpCode::Call(_)         => "call",
+            OpCode::TailCall(_)     => "tail_call",
+            OpCode::MakeClosure(_)  => "make_closure",
+            OpCode::LoadUpvalue(_)  => "load_upvalue",
+            OpCode::StoreUpvalue(_) => "store_upvalue",
+            OpCode::LoadGlobal(_)   => "load_global",
+            OpCode::StoreGlobal(_)  => "store_global",
+            OpCode::MatchFail(_)    => "match_fail",
+            OpCode::Print           => "print",
+        }
+    }
+}
+
+impl CompiledBlock {
+    fn title(&self) -> String {
+        match self.name {
+            Some(ref name)               => format!("function {}", name),
+            None if self.arms.is_empty() => String::from("<main>"),
+            None                         => String::from("function <anonymous>"),
+        }
+    }
+
+    fn constant(&self, i: u16) -> String {
+        match self.consts.get(i as usize) {
+            Some(value) => describe(*value),
+            None        => String::from("<missing>"),
+        }
+    }
+
+    fn local(&self, i: u16) -> String {
+        match self.locals.get(i as usize).map(|name| name.as_str()) {
+            Some("")   => format!("{} (argument)", i),
+            Some(name) => format!("{} ({})", i, name),
+            None       => format!("{} <missing>", i),
+        }
+    }
+
+    fn upvalue(&self, i: u16) -> String {
+        match self.upvalues.get(i as usize) {
+            Some(capture) => format!("{} ({})", i, capture.name),
+            None          => format!("{} <missing>", i),
+        }
+    }
+
+    // the operands of an instruction with indices resolved and jumps made absolute, globals
+    // are named by the outermost block
+    fn operands(&self, pc: usize, op: OpCode, globals: &[String]) -> String {
+        let target = |d: i16| format!("-> {:04}", pc as isize + d as isize);
+
+        match op {
+            OpCode::LoadConst(i)   |
+            OpCode::MakeClosure(i) => format!("{} ({})", i, self.constant(i)),
+
+            OpCode::LoadUpvalue(i)  |
+            OpCode::StoreUpvalue(i) => self.upvalue(i),
+
+            OpCode::LoadLocal(i)  |
+            OpCode::StoreLocal(i) => self.local(i),
+
+            OpCode::LoadGlobal(i)  |
+            OpCode::StoreGlobal(i) => match globals.get(i as usize) {
+                Some(name) => format!("{} ({})", i, name),
+                None       => format!("{} <missing>", i),
+            },
+
+            OpCode::TestRange(lo, hi) => format!("{} .. {}", self.constant(lo), self.constant(hi)),
+
+            OpCode::BranchTrue(d)  |
+            OpCode::BranchFalse(d) |
+            OpCode::Jump(d)        => target(d),
+
+            OpCode::Convert(width) => format!("{}", width),
+
+            OpCode::MakeTuple(n)   |
+            OpCode::MakeList(n)    |
+            OpCode::GetItem(n)     |
+            OpCode::ListRest(n)    |
+            OpCode::TestTuple(n)   |
+            OpCode::TestListLen(n) |
+            OpCode::TestListMin(n) => format!("{}", n),
+
+            OpCode::Call(n) | OpCode::TailCall(n) | OpCode::MatchFail(n) => format!("{}", n),
+
+            _ => String::new(),
+        }
+    }
+}
+
+// constants the way they'd be written in source, functions by name
+pub fn describe(value: Value) -> String {
+    match value.heap_kind() {
+        Some(&HeapKind::Str(ref s))          => format!("{:?}", s),
+        Some(&HeapKind::Function(ref block)) => format!("<{}>", block.title()),
+        _                                    => match value {
+            Value::Char(c) => format!("{:?}", c),
+            v              => format!("{}", v),
+        },
+    }
+}
+
+impl CompiledBlock {
+    // lists the block, marking where the source line changes, followed by every function
+    // among its constants
+    fn list(&self, f: &mut fmt::Formatter, globals: &[String]) -> fmt::Result {
+        writeln!(f, "{}:", self.title())?;
+
+        let mut line = None;
+
+        for (pc, op) in self.code.iter().enumerate() {
+            let here = self.lines.line(pc);
+
+            let mark = if here != line && here.is_some() {
+                line = here;
+                format!("ln {}", here.unwrap())
+            } else {
+                String::new()
+            };
+
+            let listing = format!("  {:<7} {:04}  {:<14} {}", mark, pc, op.name(), self.operands(pc, *op, globals));
+
+            writeln!(f, "{}", listing.trim_end())?
+        }
+
+        for value in self.consts.iter() {
+            if let Some(&HeapKind::Function(ref block)) = value.heap_kind() {
+                writeln!(f)?;
+                block.list(f, globals)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for CompiledBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.list(f, &self.globals)
+    }
+}
@@ -35,3 +35,115 @@ impl fmt::Display for CompileError {
         }
     }
 }
+
+#[derive(Debug)]
+pub enum RuntimeErrorValue {
+    Constant(String),
+}
+
+// a call that was active when the machine failed
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    // none for the code outside any function
+    pub function: Option<String>,
+    pub arm:      Option<usize>,
+    pub position: Option<TokenPosition>,
+}
+
+// the machine fills in the failing instruction and where it came from
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub value: RuntimeErrorValue,
+    pub position: Option<TokenPosition>,
+    pub op:       Option<OpCode>,
+    pub operands: Vec<Value>,
+    // innermost call first
+    pub trace:    Vec<TraceFrame>,
+}
+
+impl RuntimeError {
+    pub fn new(value: &str) -> RuntimeError {
+        RuntimeError {
+            value:    RuntimeErrorValue::Constant(value.to_owned()),
+            position: None,
+            op:       None,
+            operands: Vec::new(),
+            trace:    Vec::new(),
+        }
+    }
+
+    pub fn with_operands(mut self, operands: Vec<Value>) -> RuntimeError {
+        self.operands = operands;
+        self
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            RuntimeErrorValue::Constant(ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadErrorValue {
+    Constant(String),
+}
+
+// `offset` is the byte of the file where reading went wrong
+#[derive(Debug)]
+pub struct LoadError {
+    pub value:  LoadErrorValue,
+    pub offset: usize,
+}
+
+impl LoadError {
+    pub fn new(value: &str, offset: usize) -> LoadError {
+        LoadError {
+            value: LoadErrorValue::Constant(value.to_owned()),
+            offset,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            LoadErrorValue::Constant(ref s) => write!(f, "{} at byte {}", s, self.offset),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum VerifyErrorValue {
+    Constant(String),
+}
+
+// `function` is none for the block outside any function
+#[derive(Debug)]
+pub struct VerifyError {
+    pub value:    VerifyErrorValue,
+    pub pc:       usize,
+    pub function: Option<String>,
+}
+
+impl VerifyError {
+    pub fn new(value: &str, pc: usize, function: Option<String>) -> VerifyError {
+        VerifyError {
+            value: VerifyErrorValue::Constant(value.to_owned()),
+            pc,
+            function,
+        }
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let function = self.function.clone().unwrap_or(String::from("<main>"));
+
+        match self.value {
+            VerifyErrorValue::Constant(ref s) => write!(f, "{} at {:04} in {}", s, self.pc, function),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::cmp;
+use std::ptr;
+
+use super::*;
+
+pub const GC_THRESHOLD: usize = 1024;
+
+// every object the compiler and the machine allocate, threaded through `HeapObject.next`
+#[derive(Debug)]
+pub struct Heap {
+    pub next_object: *mut HeapObject,
+    pub objects:     usize,
+    // how many objects can be alive before allocating collects
+    pub threshold:   usize,
+    next_collection: usize,
+}
+
+impl Heap {
+    pub fn new() -> Heap {
+        Heap {
+            next_object:     ptr::null_mut(),
+            objects:         0,
+            threshold:       GC_THRESHOLD,
+            next_collection: GC_THRESHOLD,
+        }
+    }
+
+    pub fn allocate(&mut self, kind: HeapKind) -> Value {
+        let obj = Box::into_raw(Box::new(HeapObject {
+            next: self.next_object,
+            marked: false,
+            kind,
+        }));
+
+        self.next_object = obj;
+        self.objects    += 1;
+
+        Value::HeapObject(obj)
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.objects >= self.next_collection
+    }
+
+    pub fn set_threshold(&mut self, threshold: usize) {
+        self.threshold       = threshold;
+        self.next_collection = cmp::max(threshold, self.objects);
+    }
+
+    // frees whatever wasn't marked and clears the mark on the rest, the heap then
+    // has to double before the next collection
+    pub fn sweep(&mut self) {
+        let mut link: *mut *mut HeapObject = &mut self.next_object;
+
+        unsafe {
+            while !(*link).is_null() {
+                let obj = *link;
+
+                if (*obj).marked {
+                    (*obj).marked = false;
+                    link = &mut (*obj).next;
+                } else {
+                    *link = (*obj).next;
+                    drop(Box::from_raw(obj));
+
+                    self.objects -= 1;
+                }
+            }
+        }
+
+        self.next_collection = cmp::max(self.threshold, self.objects * 2);
+    }
+}
+
+impl Drop for Heap {
+    fn drop(&mut self) {
+        let mut obj = self.next_object;
+
+        while !obj.is_null() {
+            let next = unsafe { (*obj).next };
+            drop(unsafe { Box::from_raw(obj) });
+            obj = next;
+        }
+    }
+}
+
+// marks everything reachable from the roots, walking a worklist rather than recursing
+// so deeply nested lists can't overflow the stack
+pub fn mark(roots: &[Value]) {
+    let mut gray = roots.to_vec();
+
+    while let Some(value) = gray.pop() {
+        if let Value::HeapObject(p) = value {
+            let obj = unsafe { &mut *p };
+
+            if obj.marked {
+                continue
+            }
+
+            obj.marked = true;
+
+            match obj.kind {
+                HeapKind::Tuple(ref items) | HeapKind::List(ref items) => gray.extend(items.iter().cloned()),
+                HeapKind::Function(ref block)                          => gray.extend(block.consts.iter().cloned()),
+                HeapKind::Closure(function, ref upvalues)              => {
+                    gray.push(function);
+                    gray.extend(upvalues.iter().cloned())
+                },
+                // an open upvalue's value sits in a frame's locals, which are roots already
+                HeapKind::Upvalue(Upvalue::Closed(value))              => gray.push(value),
+                HeapKind::Upvalue(Upvalue::Open(_))                    => (),
+                HeapKind::Str(_)                                       => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use super::super::testing::compile;
+
+    fn text(heap: &mut Heap, s: &str) -> Value {
+        heap.allocate(HeapKind::Str(Rc::new(s.to_string())))
+    }
+
+    fn marked(value: Value) -> bool {
+        match value {
+            Value::HeapObject(p) => unsafe { (*p).marked },
+            _                    => false,
+        }
+    }
+
+    #[test]
+    fn sweep_frees_only_the_unreachable() {
+        let mut heap = Heap::new();
+
+        let kept = text(&mut heap, "kept");
+        let list = heap.allocate(HeapKind::List(vec![kept]));
+        text(&mut heap, "garbage");
+
+        mark(&[list]);
+        heap.sweep();
+
+        assert_eq!(heap.objects, 2);
+        assert!(!marked(list) && !marked(kept));
+        assert_eq!(format!("{}", list), "[kept]");
+    }
+
+    #[test]
+    fn deep_nesting_is_marked_without_recursion() {
+        let mut heap = Heap::new();
+        let mut list = heap.allocate(HeapKind::List(Vec::new()));
+
+        for _ in 0 .. 100000 {
+            list = heap.allocate(HeapKind::List(vec![list]))
+        }
+
+        mark(&[list]);
+        heap.sweep();
+
+        assert_eq!(heap.objects, 100001);
+    }
+
+    #[test]
+    fn collections_wait_for_the_heap_to_double() {
+        let mut heap = Heap::new();
+        heap.set_threshold(4);
+
+        let live = (0 .. 4).map(|i| text(&mut heap, &i.to_string())).collect::<Vec<_>>();
+        assert!(heap.should_collect());
+
+        mark(&live);
+        heap.sweep();
+        assert!(!heap.should_collect());
+
+        for i in 0 .. 4 {
+            text(&mut heap, &i.to_string());
+        }
+        assert!(heap.should_collect());
+    }
+
+    #[test]
+    fn running_collects_garbage() {
+        let source = "count := {\n  |(0, acc)| acc\n  |(n, acc)| count (n - 1, acc + 1)\n}\ncount (10000, 0)\n";
+
+        let mut vm = VirtualMachine::new();
+        vm.set_gc_threshold(64);
+
+        let compiled = compile(&mut vm, source).unwrap();
+
+        assert_eq!(vm.execute(&compiled).unwrap(), Value::Int(10000));
+        assert!(vm.heap.borrow().objects < 256, "{} objects left", vm.heap.borrow().objects);
+    }
+}
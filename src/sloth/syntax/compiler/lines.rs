@@ -0,0 +1,47 @@
+use super::*;
+
+// maps instructions back to the source, storing a position only where it changes
+// so a run of instructions from one expression costs a single entry
+#[derive(Debug, Clone, Default)]
+pub struct LineTable {
+    // the first pc each position applies to, in increasing order
+    entries: Vec<(u32, TokenPosition)>,
+}
+
+impl LineTable {
+    pub fn new() -> LineTable {
+        LineTable {
+            entries: Vec::new(),
+        }
+    }
+
+    // instructions have to be recorded in the order they are emitted
+    pub fn record(&mut self, pc: usize, position: TokenPosition) {
+        match self.entries.last() {
+            Some(&(_, last)) if last == position => (),
+            _                                    => self.entries.push((pc as u32, position)),
+        }
+    }
+
+    pub fn position(&self, pc: usize) -> Option<TokenPosition> {
+        let pc = pc as u32;
+
+        match self.entries.binary_search_by_key(&pc, |&(start, _)| start) {
+            Ok(i)  => Some(self.entries[i].1),
+            Err(0) => None,
+            Err(i) => Some(self.entries[i - 1].1),
+        }
+    }
+
+    pub fn line(&self, pc: usize) -> Option<usize> {
+        self.position(pc).map(|p| p.line)
+    }
+
+    pub fn entries(&self) -> &[(u32, TokenPosition)] {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+}
@@ -0,0 +1,270 @@
+use std::rc::Rc;
+
+use super::*;
+
+// where a matched value lives, relative to the arguments of the call
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    Arg(u16),
+    Item(Rc<Access>, u16),
+    Rest(Rc<Access>, u16),
+}
+
+#[derive(Debug, Clone)]
+pub enum Test {
+    Literal(Rc<Expression>),
+    Range(Rc<Expression>, Rc<Expression>),
+    Tuple(usize),
+    ListLen(usize),
+    ListMin(usize),
+}
+
+#[derive(Debug)]
+pub enum Decision {
+    Fail,
+    Leaf {
+        arm:       usize,
+        bindings:  Vec<(Rc<String>, Access)>,
+        otherwise: Option<Box<Decision>>,
+    },
+    Test {
+        access:  Access,
+        test:    Test,
+        success: Box<Decision>,
+        failure: Box<Decision>,
+    },
+}
+
+// an arm as it is being taken apart: the refutable patterns left to test and
+// the names it binds along the way
+#[derive(Debug, Clone)]
+struct Row {
+    tests:    Vec<(Access, Pattern)>,
+    bindings: Vec<(Rc<String>, Access)>,
+    arm:      usize,
+    guarded:  bool,
+}
+
+impl Row {
+    fn push(&mut self, access: Access, pattern: &Pattern) {
+        match *pattern {
+            Pattern::Wildcard          => (),
+            Pattern::Bind(ref name, _) => self.bindings.push((name.clone(), access)),
+
+            Pattern::As(ref name, ref pattern, _) => {
+                self.bindings.push((name.clone(), access.clone()));
+                self.push(access, pattern)
+            },
+
+            ref pattern => self.tests.push((access, pattern.clone())),
+        }
+    }
+
+    fn push_items(&mut self, access: &Access, items: &[Pattern]) {
+        for (i, item) in items.iter().enumerate() {
+            self.push(Access::Item(Rc::new(access.clone()), i as u16), item)
+        }
+    }
+
+    fn pattern(&self, access: &Access) -> Option<&Pattern> {
+        self.tests.iter().find(|(a, _)| a == access).map(|(_, p)| p)
+    }
+
+    fn without(&self, access: &Access) -> Row {
+        let mut row = self.clone();
+        row.tests.retain(|(a, _)| a != access);
+        row
+    }
+}
+
+// arms are tried in order: each test splits the remaining arms into those that can
+// still match when it succeeds and those that can when it fails, so no value is
+// examined twice along any path
+pub fn decision_tree(arms: &[Rc<Expression>]) -> Decision {
+    let rows = arms.iter().enumerate().map(|(i, arm)| {
+        let mut row = Row {
+            tests:    Vec::new(),
+            bindings: Vec::new(),
+            arm:      i,
+            guarded:  false,
+        };
+
+        if let Expression::Arm(ref arm) = **arm {
+            row.guarded = arm.guard.is_some();
+
+            for (j, param) in arm.params.iter().enumerate() {
+                row.push(Access::Arg(j as u16), param)
+            }
+        }
+
+        row
+    }).collect();
+
+    decide(rows)
+}
+
+fn decide(mut rows: Vec<Row>) -> Decision {
+    if rows.is_empty() {
+        return Decision::Fail
+    }
+
+    if rows[0].tests.is_empty() {
+        let first = rows.remove(0);
+
+        let otherwise = if first.guarded {
+            Some(Box::new(decide(rows)))
+        } else {
+            None
+        };
+
+        return Decision::Leaf {
+            arm:      first.arm,
+            bindings: first.bindings,
+            otherwise,
+        }
+    }
+
+    let (access, test) = {
+        let (ref access, ref pattern) = rows[0].tests[0];
+        (access.clone(), test_for(pattern))
+    };
+
+    let success = rows.iter().filter_map(|r| specialize(r, &access, &test)).collect();
+    let failure = rows.iter().filter(|r| !excluded(r, &access, &test)).cloned().collect();
+
+    Decision::Test {
+        access,
+        test,
+        success: Box::new(decide(success)),
+        failure: Box::new(decide(failure)),
+    }
+}
+
+fn test_for(pattern: &Pattern) -> Test {
+    match *pattern {
+        Pattern::Literal(ref e)        => Test::Literal(e.clone()),
+        Pattern::Range(ref lo, ref hi) => Test::Range(lo.clone(), hi.clone()),
+        Pattern::Tuple(ref items)      => Test::Tuple(items.len()),
+        Pattern::List(ref items, None) => Test::ListLen(items.len()),
+        Pattern::List(ref items, _)    => Test::ListMin(items.len()),
+        _                              => unreachable!(),
+    }
+}
+
+// the row as it stands once `test` has succeeded on `access`, if it can still match
+fn specialize(row: &Row, access: &Access, test: &Test) -> Option<Row> {
+    let pattern = match row.pattern(access) {
+        Some(pattern) => pattern,
+        None          => return Some(row.clone()),
+    };
+
+    let mut decided = row.without(access);
+
+    match (test, pattern) {
+        (Test::Literal(v), Pattern::Literal(w)) if v.same_literal(w) => Some(decided),
+        (Test::Literal(v), Pattern::Range(lo, hi)) if v.within(lo, hi) => Some(decided),
+
+        (Test::Range(lo, hi), Pattern::Range(l, h)) => if lo.same_literal(l) && hi.same_literal(h) {
+            Some(decided)
+        } else {
+            Some(row.clone())
+        },
+
+        (Test::Range(lo, hi), Pattern::Literal(w)) if w.within(lo, hi) => Some(row.clone()),
+
+        (Test::Tuple(n), Pattern::Tuple(items)) if items.len() == *n => {
+            decided.push_items(access, items);
+            Some(decided)
+        },
+
+        (Test::ListLen(n), Pattern::List(items, rest)) => match rest {
+            None if items.len() == *n => {
+                decided.push_items(access, items);
+                Some(decided)
+            },
+
+            Some(rest) if items.len() <= *n => {
+                decided.push_items(access, items);
+                decided.push(Access::Rest(Rc::new(access.clone()), items.len() as u16), rest);
+                Some(decided)
+            },
+
+            _ => None,
+        },
+
+        (Test::ListMin(m), Pattern::List(items, rest)) => match rest {
+            Some(rest) if items.len() <= *m => {
+                decided.push_items(access, items);
+                decided.push(Access::Rest(Rc::new(access.clone()), items.len() as u16), rest);
+                Some(decided)
+            },
+
+            _ if items.len() >= *m => Some(row.clone()),
+            _                      => None,
+        },
+
+        _ => None,
+    }
+}
+
+// whether `test` failing on `access` rules the row out
+fn excluded(row: &Row, access: &Access, test: &Test) -> bool {
+    let pattern = match row.pattern(access) {
+        Some(pattern) => pattern,
+        None          => return false,
+    };
+
+    match (test, pattern) {
+        (Test::Literal(v), Pattern::Literal(w))        => v.same_literal(w),
+        (Test::Range(lo, hi), Pattern::Range(l, h))    => lo.same_literal(l) && hi.same_literal(h),
+        (Test::Range(lo, hi), Pattern::Literal(w))     => w.within(lo, hi),
+        (Test::Tuple(n), Pattern::Tuple(items))        => items.len() == *n,
+        (Test::ListLen(n), Pattern::List(items, None)) => items.len() == *n,
+        (Test::ListMin(m), Pattern::List(items, _))    => items.len() >= *m,
+        _                                              => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{run, shown};
+
+    fn ran(source: &str) -> String {
+        shown(source).unwrap()
+    }
+
+    #[test]
+    fn arms_are_tried_in_order() {
+        let describe = "describe := {\n  |0|            \"zero\"\n  |n if n > 100| \"big\"\n  |x @ 1..9|     \"digit\"\n  |_|            \"anything\"\n}\n";
+
+        for &(arg, expected) in &[("0", "zero"), ("500", "big"), ("5", "digit"), ("9", "digit"), ("50", "anything")] {
+            assert_eq!(ran(&format!("{}describe {}\n", describe, arg)), expected, "{}", arg);
+        }
+    }
+
+    #[test]
+    fn guards_fall_through() {
+        let pick = "pick := {\n  |n if n > 10| 1\n  |n if n > 5|  2\n  |_|           3\n}\n";
+
+        assert_eq!(ran(&format!("{}pick 20\n", pick)), "1");
+        assert_eq!(ran(&format!("{}pick 7\n", pick)), "2");
+        assert_eq!(ran(&format!("{}pick 1\n", pick)), "3");
+    }
+
+    #[test]
+    fn nested_patterns_bind_their_parts() {
+        let f = "f := {\n  |(a, [b, ..rest])| a + b\n  |(_, [])|          0\n}\n";
+
+        assert_eq!(ran(&format!("{}f (1, [2, 3])\n", f)), "3");
+        assert_eq!(ran(&format!("{}f (1, [])\n", f)), "0");
+        assert_eq!(ran("tail := {\n  |[_, ..rest]| rest\n  |[]|          []\n}\nxs := [1, 2, 3]\ntail xs\n"), "[2, 3]");
+    }
+
+    #[test]
+    fn no_arm_matches() {
+        match run("h := {\n  |0| 1\n}\nh 1\n") {
+            Ok(value) => panic!("ran to {}", value),
+            Err(err)  => assert_eq!(format!("{}", err), "no arm matches: 1"),
+        }
+    }
+}
@@ -1,13 +1,30 @@
 pub mod error;
 pub mod value;
+pub mod heap;
+pub mod number;
+pub mod lines;
 pub mod compiler;
+pub mod matching;
 pub mod vm;
+pub mod disassemble;
+pub mod bytecode;
+pub mod verify;
 
 pub use super::*;
 
 pub use self::error::*;
 pub use self::value::*;
+pub use self::heap::*;
+pub use self::number::*;
+pub use self::lines::*;
 pub use self::compiler::*;
+pub use self::matching::*;
 pub use self::vm::*;
+pub use self::disassemble::*;
+pub use self::bytecode::*;
+pub use self::verify::*;
 
 pub type CompileResult<T> = Result<T, CompileError>;
+pub type RunResult<T>     = Result<T, RuntimeError>;
+pub type LoadResult<T>    = Result<T, LoadError>;
+pub type VerifyResult<T>  = Result<T, VerifyError>;
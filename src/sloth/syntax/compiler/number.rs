@@ -0,0 +1,348 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::*;
+
+// the declared widths a number can have at runtime, `Value::Int` and `Value::Float`
+// being the numbers nobody gave a width
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Width {
+    I08, I16, I32, I64, I128,
+    U08, U16, U32, U64, U128,
+    F32, F64,
+}
+
+impl Width {
+    pub fn from_type(t: &Type) -> Option<Width> {
+        match *t {
+            Type::I08  => Some(Width::I08),
+            Type::I16  => Some(Width::I16),
+            Type::I32  => Some(Width::I32),
+            Type::I64  => Some(Width::I64),
+            Type::I128 => Some(Width::I128),
+            Type::U08  => Some(Width::U08),
+            Type::U16  => Some(Width::U16),
+            Type::U32  => Some(Width::U32),
+            Type::U64  => Some(Width::U64),
+            Type::U128 => Some(Width::U128),
+            Type::F32  => Some(Width::F32),
+            Type::F64  => Some(Width::F64),
+
+            Type::Mut(Some(ref t)) => Width::from_type(t),
+            _                      => None,
+        }
+    }
+}
+
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Width::I08  => "i08",
+            Width::I16  => "i16",
+            Width::I32  => "i32",
+            Width::I64  => "i64",
+            Width::I128 => "i128",
+            Width::U08  => "u08",
+            Width::U16  => "u16",
+            Width::U32  => "u32",
+            Width::U64  => "u64",
+            Width::U128 => "u128",
+            Width::F32  => "f32",
+            Width::F64  => "f64",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+// an integer of any width, only u128 goes past what i128 holds
+#[derive(Clone, Copy)]
+enum Wide {
+    Signed(i128),
+    Unsigned(u128),
+}
+
+macro_rules! narrow {
+    ($wide:expr, $t:ty) => {
+        match $wide {
+            Wide::Signed(n)   => <$t>::try_from(n).ok(),
+            Wide::Unsigned(n) => <$t>::try_from(n).ok(),
+        }
+    }
+}
+
+// applies a checked integer operation to two integers of one variant, and a float
+// operation to two floats of one variant
+macro_rules! same_width {
+    ($a:expr, $b:expr, |$x:ident, $y:ident| $int:expr, |$f:ident, $g:ident| $float:expr) => {
+        match ($a, $b) {
+            (Value::Int($x), Value::Int($y))   => $int.map(Value::Int),
+            (Value::I08($x), Value::I08($y))   => $int.map(Value::I08),
+            (Value::I16($x), Value::I16($y))   => $int.map(Value::I16),
+            (Value::I32($x), Value::I32($y))   => $int.map(Value::I32),
+            (Value::I64($x), Value::I64($y))   => $int.map(Value::I64),
+            (Value::I128($x), Value::I128($y)) => $int.map(Value::I128),
+            (Value::U08($x), Value::U08($y))   => $int.map(Value::U08),
+            (Value::U16($x), Value::U16($y))   => $int.map(Value::U16),
+            (Value::U32($x), Value::U32($y))   => $int.map(Value::U32),
+            (Value::U64($x), Value::U64($y))   => $int.map(Value::U64),
+            (Value::U128($x), Value::U128($y)) => $int.map(Value::U128),
+
+            (Value::Float($f), Value::Float($g)) => Some(Value::Float($float)),
+            (Value::F64($f), Value::F64($g))     => Some(Value::F64($float)),
+            (Value::F32($f), Value::F32($g))     => Some(Value::F32($float)),
+
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Value {
+    pub fn is_number(self) -> bool {
+        match self {
+            Value::Int(_) | Value::Float(_) => true,
+            v                               => v.width().is_some(),
+        }
+    }
+
+    pub fn width(self) -> Option<Width> {
+        match self {
+            Value::I08(_)  => Some(Width::I08),
+            Value::I16(_)  => Some(Width::I16),
+            Value::I32(_)  => Some(Width::I32),
+            Value::I64(_)  => Some(Width::I64),
+            Value::I128(_) => Some(Width::I128),
+            Value::U08(_)  => Some(Width::U08),
+            Value::U16(_)  => Some(Width::U16),
+            Value::U32(_)  => Some(Width::U32),
+            Value::U64(_)  => Some(Width::U64),
+            Value::U128(_) => Some(Width::U128),
+            Value::F32(_)  => Some(Width::F32),
+            Value::F64(_)  => Some(Width::F64),
+            _              => None,
+        }
+    }
+
+    fn wide(self) -> Option<Wide> {
+        match self {
+            Value::Int(n)  => Some(Wide::Signed(n)),
+            Value::I08(n)  => Some(Wide::Signed(n as i128)),
+            Value::I16(n)  => Some(Wide::Signed(n as i128)),
+            Value::I32(n)  => Some(Wide::Signed(n as i128)),
+            Value::I64(n)  => Some(Wide::Signed(n as i128)),
+            Value::I128(n) => Some(Wide::Signed(n)),
+            Value::U08(n)  => Some(Wide::Unsigned(n as u128)),
+            Value::U16(n)  => Some(Wide::Unsigned(n as u128)),
+            Value::U32(n)  => Some(Wide::Unsigned(n as u128)),
+            Value::U64(n)  => Some(Wide::Unsigned(n as u128)),
+            Value::U128(n) => Some(Wide::Unsigned(n)),
+            _              => None,
+        }
+    }
+
+    // an integer of any width as a position in a list, if it can be one
+    pub fn as_index(self) -> Option<usize> {
+        self.wide().and_then(|wide| narrow!(wide, usize))
+    }
+
+    fn float(self) -> Option<f64> {
+        match self.wide() {
+            Some(Wide::Signed(n))   => Some(n as f64),
+            Some(Wide::Unsigned(n)) => Some(n as f64),
+            None                    => match self {
+                Value::Float(n) | Value::F64(n) => Some(n),
+                Value::F32(n)                   => Some(n as f64),
+                _                               => None,
+            },
+        }
+    }
+
+    // floats are truncated towards zero, anything that doesn't fit is an error
+    pub fn convert(self, width: Width) -> RunResult<Value> {
+        let wide = match self.wide() {
+            Some(wide) => Some(wide),
+            None       => match self.float() {
+                Some(n) if n.is_finite() && n.trunc() >= 0.0 => if n.trunc() < u128::MAX as f64 { Some(Wide::Unsigned(n as u128)) } else { None },
+                Some(n) if n.is_finite()                     => if n.trunc() >= i128::MIN as f64 { Some(Wide::Signed(n as i128)) } else { None },
+                _                                            => None,
+            },
+        };
+
+        let converted = match width {
+            Width::F32 => self.float().map(|n| Value::F32(n as f32)),
+            Width::F64 => self.float().map(Value::F64),
+
+            _ => wide.and_then(|wide| match width {
+                Width::I08  => narrow!(wide, i8).map(Value::I08),
+                Width::I16  => narrow!(wide, i16).map(Value::I16),
+                Width::I32  => narrow!(wide, i32).map(Value::I32),
+                Width::I64  => narrow!(wide, i64).map(Value::I64),
+                Width::I128 => narrow!(wide, i128).map(Value::I128),
+                Width::U08  => narrow!(wide, u8).map(Value::U08),
+                Width::U16  => narrow!(wide, u16).map(Value::U16),
+                Width::U32  => narrow!(wide, u32).map(Value::U32),
+                Width::U64  => narrow!(wide, u64).map(Value::U64),
+                Width::U128 => narrow!(wide, u128).map(Value::U128),
+                Width::F32 | Width::F64 => unreachable!(),
+            }),
+        };
+
+        converted.ok_or_else(|| RuntimeError::new(&format!("{} doesn't fit in {}", self, width)))
+    }
+
+    // a number without a width taking one, which only a conversion may truncate a float to
+    fn take_width(self, width: Width) -> RunResult<Value> {
+        match (self, width) {
+            (Value::Float(_), Width::F32) | (Value::Float(_), Width::F64) => self.convert(width),
+            (Value::Float(_), _) => Err(RuntimeError::new(&format!("float {} can't be a {} without a conversion", self, width))),
+            _                    => self.convert(width),
+        }
+    }
+
+    // brings two numbers to one width, a number without one takes the other's
+    fn coerce(self, other: Value) -> RunResult<(Value, Value)> {
+        match (self, other) {
+            (Value::Int(a), Value::Float(_)) => Ok((Value::Float(a as f64), other)),
+            (Value::Float(_), Value::Int(b)) => Ok((self, Value::Float(b as f64))),
+
+            (Value::Int(_), Value::Int(_)) | (Value::Float(_), Value::Float(_)) => Ok((self, other)),
+
+            _ => match (self.width(), other.width()) {
+                (Some(a), Some(b)) if a == b                           => Ok((self, other)),
+                (None, Some(b)) if self.is_number()                    => Ok((self.take_width(b)?, other)),
+                (Some(a), None) if other.is_number()                   => Ok((self, other.take_width(a)?)),
+                (Some(a), Some(b))                                     => Err(RuntimeError::new(&format!("mismatched widths: {} and {}", a, b))),
+                _                                                      => Err(RuntimeError::new(&format!("expected numbers, found {} and {}", self, other))),
+            },
+        }
+    }
+
+    pub fn arith(self, op: &Operand, other: Value) -> RunResult<Value> {
+        let (a, b) = self.coerce(other)?;
+
+        let zero = match b.wide() {
+            Some(Wide::Signed(n))   => n == 0,
+            Some(Wide::Unsigned(n)) => n == 0,
+            None                    => false,
+        };
+
+        if zero && (*op == Operand::Div || *op == Operand::Mod) {
+            return Err(RuntimeError::new("division by zero"))
+        }
+
+        if let (&Operand::Pow, Some(Wide::Signed(n))) = (op, b.wide()) {
+            if n < 0 {
+                return Err(RuntimeError::new(&format!("negative exponent: {} ^ {}", self, other)))
+            }
+        }
+
+        // an int power's exponent, one past `u32` overflows anything but 0 and 1 anyway
+        let exponent = match b.wide() {
+            Some(Wide::Signed(n))   => u32::try_from(n).ok(),
+            Some(Wide::Unsigned(n)) => u32::try_from(n).ok(),
+            None                    => None,
+        };
+
+        let result = match *op {
+            Operand::Add => same_width!(a, b, |x, y| x.checked_add(y), |x, y| x + y),
+            Operand::Sub => same_width!(a, b, |x, y| x.checked_sub(y), |x, y| x - y),
+            Operand::Mul => same_width!(a, b, |x, y| x.checked_mul(y), |x, y| x * y),
+            Operand::Div => same_width!(a, b, |x, y| x.checked_div(y), |x, y| x / y),
+            Operand::Mod => same_width!(a, b, |x, y| x.checked_rem(y), |x, y| x % y),
+            Operand::Pow => same_width!(a, b, |x, _y| exponent.and_then(|e| x.checked_pow(e)), |x, y| x.powf(y)),
+            ref op       => return Err(RuntimeError::new(&format!("not arithmetic: {}", op.as_str()))),
+        };
+
+        result.ok_or_else(|| {
+            let width = a.width().map_or(String::from("int"), |w| format!("{}", w));
+            RuntimeError::new(&format!("overflow: {} {} {} doesn't fit in {}", self, op.as_str(), other, width))
+        })
+    }
+
+    pub fn negate(self) -> RunResult<Value> {
+        match self {
+            Value::Float(n) => Ok(Value::Float(-n)),
+            Value::F64(n)   => Ok(Value::F64(-n)),
+            Value::F32(n)   => Ok(Value::F32(-n)),
+            v if v.is_number() => {
+                let zero = match v.width() {
+                    Some(width) => Value::Int(0).convert(width)?,
+                    None        => Value::Int(0),
+                };
+
+                zero.arith(&Operand::Sub, v)
+            },
+            v => Err(RuntimeError::new(&format!("can't negate {}", v))),
+        }
+    }
+
+    // ordering between numbers of one width, between chars, and nothing else
+    pub fn order(self, other: Value) -> RunResult<Option<Ordering>> {
+        if let (Value::Char(a), Value::Char(b)) = (self, other) {
+            return Ok(Some(a.cmp(&b)))
+        }
+
+        let (a, b) = self.coerce(other)?;
+
+        Ok(match (a.wide(), b.wide()) {
+            (Some(Wide::Signed(a)), Some(Wide::Signed(b)))     => Some(a.cmp(&b)),
+            (Some(Wide::Unsigned(a)), Some(Wide::Unsigned(b))) => Some(a.cmp(&b)),
+            _                                                  => a.float().and_then(|a| b.float().and_then(|b| a.partial_cmp(&b))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(result: RunResult<Value>) -> String {
+        match result {
+            Ok(value) => panic!("gave {}", value),
+            Err(err)  => format!("{}", err),
+        }
+    }
+
+    #[test]
+    fn arithmetic_is_checked_in_its_width() {
+        assert_eq!(Value::U08(200).arith(&Operand::Add, Value::U08(55)).unwrap(), Value::U08(255));
+        assert_eq!(failure(Value::U08(200).arith(&Operand::Add, Value::U08(56))), "overflow: 200 + 56 doesn't fit in u08");
+        assert_eq!(failure(Value::I08(-128).arith(&Operand::Sub, Value::I08(1))), "overflow: -128 - 1 doesn't fit in i08");
+        assert_eq!(failure(Value::U32(0).arith(&Operand::Sub, Value::U32(1))), "overflow: 0 - 1 doesn't fit in u32");
+        assert_eq!(failure(Value::Int(1).arith(&Operand::Div, Value::Int(0))), "division by zero");
+    }
+
+    #[test]
+    fn numbers_without_a_width_take_the_others() {
+        assert_eq!(Value::U08(200).arith(&Operand::Add, Value::Int(55)).unwrap(), Value::U08(255));
+        assert_eq!(Value::Int(1).arith(&Operand::Add, Value::F32(0.5)).unwrap(), Value::F32(1.5));
+        assert_eq!(Value::Float(0.5).arith(&Operand::Add, Value::F64(1.0)).unwrap(), Value::F64(1.5));
+        assert_eq!(failure(Value::U08(1).arith(&Operand::Add, Value::I08(1))), "mismatched widths: u08 and i08");
+    }
+
+    #[test]
+    fn floats_only_truncate_in_a_conversion() {
+        assert_eq!(failure(Value::U08(1).arith(&Operand::Add, Value::Float(1.5))), "float 1.5 can't be a u08 without a conversion");
+        assert_eq!(Value::Float(1.5).convert(Width::U08).unwrap(), Value::U08(1));
+        assert_eq!(Value::Float(-1.5).convert(Width::I08).unwrap(), Value::I08(-1));
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(Value::Int(2).arith(&Operand::Pow, Value::Int(10)).unwrap(), Value::Int(1024));
+        assert_eq!(Value::U08(2).arith(&Operand::Pow, Value::U08(7)).unwrap(), Value::U08(128));
+        assert_eq!(failure(Value::U08(2).arith(&Operand::Pow, Value::U08(8))), "overflow: 2 ^ 8 doesn't fit in u08");
+        assert_eq!(failure(Value::Int(2).arith(&Operand::Pow, Value::Int(-1))), "negative exponent: 2 ^ -1");
+        assert_eq!(Value::Float(2.0).arith(&Operand::Pow, Value::Float(-1.0)).unwrap(), Value::Float(0.5));
+    }
+
+    #[test]
+    fn conversions_have_to_fit() {
+        assert_eq!(Value::Int(255).convert(Width::U08).unwrap(), Value::U08(255));
+        assert_eq!(Value::U128(u128::MAX).convert(Width::U128).unwrap(), Value::U128(u128::MAX));
+        assert_eq!(failure(Value::Int(256).convert(Width::U08)), "256 doesn't fit in u08");
+        assert_eq!(failure(Value::Int(-1).convert(Width::U128)), "-1 doesn't fit in u128");
+        assert_eq!(failure(Value::U128(u128::MAX).convert(Width::I128)), "340282366920938463463374607431768211455 doesn't fit in i128");
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 use std::mem;
 use std::rc::Rc;
@@ -9,6 +10,19 @@ use super::*;
 pub enum HeapKind {
     Str(Rc<String>),
     Function(CompiledBlock),
+    // a function and the upvalues it captured, in the order of its `upvalues`
+    Closure(Value, Vec<Value>),
+    Upvalue(Upvalue),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+}
+
+// a captured variable points into the frame that declared it while that frame runs,
+// and holds on to the value once it returns
+#[derive(Debug, Clone, Copy)]
+pub enum Upvalue {
+    Open(*mut Value),
+    Closed(Value),
 }
 
 #[derive(Debug, Clone)]
@@ -21,8 +35,20 @@ pub struct HeapObject {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Value {
     Bool(bool),
-    Int(i64),
+    Int(i128),
     Float(f64),
+    I08(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    U08(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    F32(f32),
+    F64(f64),
     Char(char),
     Null,
     HeapObject(*mut HeapObject),
@@ -40,14 +66,29 @@ impl Hash for Value {
                 state.write_u8(b as u8)
             }
 
-            Value::Int(n) => {
+            Value::Int(n) | Value::I128(n) => {
+                state.write_u8(2);
+                state.write_i128(n)
+            }
+
+            Value::I08(n) => { state.write_u8(2); state.write_i8(n) }
+            Value::I16(n) => { state.write_u8(2); state.write_i16(n) }
+            Value::I32(n) => { state.write_u8(2); state.write_i32(n) }
+            Value::I64(n) => { state.write_u8(2); state.write_i64(n) }
+            Value::U08(n) => { state.write_u8(2); state.write_u8(n) }
+            Value::U16(n) => { state.write_u8(2); state.write_u16(n) }
+            Value::U32(n) => { state.write_u8(2); state.write_u32(n) }
+            Value::U64(n) => { state.write_u8(2); state.write_u64(n) }
+            Value::U128(n) => { state.write_u8(2); state.write_u128(n) }
+
+            Value::Float(n) | Value::F64(n) => {
                 state.write_u8(2);
-                state.write_u64(unsafe {mem::transmute(n)})
+                state.write_u64(n.to_bits())
             }
 
-            Value::Float(n) => {
+            Value::F32(n) => {
                 state.write_u8(2);
-                state.write_u64(unsafe {mem::transmute(n)})
+                state.write_u32(n.to_bits())
             }
 
             Value::Char(n) => {
@@ -67,6 +108,37 @@ impl Value {
     pub fn truthy(self) -> bool {
         self != Value::Null && self != Value::Bool(false)
     }
+
+    // like `==`, but strings, tuples and lists compare by content rather than by object,
+    // and numbers by value whatever their width
+    pub fn equals(self, other: Value) -> bool {
+        match (self, other) {
+            (a, b) if a.is_number() && b.is_number() => a.compare(b) == Some(Ordering::Equal),
+
+            (Value::HeapObject(a), Value::HeapObject(b)) => match unsafe { (&(*a).kind, &(*b).kind) } {
+                (&HeapKind::Str(ref a), &HeapKind::Str(ref b))     => a == b,
+                (&HeapKind::Tuple(ref a), &HeapKind::Tuple(ref b)) |
+                (&HeapKind::List(ref a), &HeapKind::List(ref b))   => {
+                    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equals(*b))
+                },
+                _ => a == b,
+            },
+
+            (a, b) => a == b,
+        }
+    }
+
+    // like `order`, with values that don't compare giving nothing
+    pub fn compare(self, other: Value) -> Option<Ordering> {
+        self.order(other).unwrap_or(None)
+    }
+
+    pub fn heap_kind(&self) -> Option<&HeapKind> {
+        match *self {
+            Value::HeapObject(p) => Some(unsafe { &(*p).kind }),
+            _                    => None,
+        }
+    }
 }
 
 impl Display for Value {
@@ -76,12 +148,42 @@ impl Display for Value {
             Value::Bool(b)       => write!(f, "{}", b)?,
             Value::Int(n)        => write!(f, "{}", n)?,
             Value::Float(n)      => write!(f, "{}", n)?,
+            Value::I08(n)        => write!(f, "{}", n)?,
+            Value::I16(n)        => write!(f, "{}", n)?,
+            Value::I32(n)        => write!(f, "{}", n)?,
+            Value::I64(n)        => write!(f, "{}", n)?,
+            Value::I128(n)       => write!(f, "{}", n)?,
+            Value::U08(n)        => write!(f, "{}", n)?,
+            Value::U16(n)        => write!(f, "{}", n)?,
+            Value::U32(n)        => write!(f, "{}", n)?,
+            Value::U64(n)        => write!(f, "{}", n)?,
+            Value::U128(n)       => write!(f, "{}", n)?,
+            Value::F32(n)        => write!(f, "{}", n)?,
+            Value::F64(n)        => write!(f, "{}", n)?,
             Value::Char(n)       => write!(f, "{}", n)?,
             Value::HeapObject(p) => {
                 let obj = unsafe { &*p };
                 match obj.kind {
                     HeapKind::Str(ref s)         => write!(f, "{}", s)?,
-                    HeapKind::Function(_) => write!(f, "lambda<{:#?}>", p)?,
+                    HeapKind::Function(_) |
+                    HeapKind::Closure(..)        => write!(f, "lambda<{:#?}>", p)?,
+                    HeapKind::Upvalue(ref up)    => match *up {
+                        Upvalue::Open(v)   => write!(f, "{}", unsafe { *v })?,
+                        Upvalue::Closed(v) => write!(f, "{}", v)?,
+                    },
+                    HeapKind::Tuple(ref items)   => {
+                        write!(f, "(")?;
+                        write_items(f, items)?;
+                        if items.len() == 1 {
+                            write!(f, ",")?;
+                        }
+                        write!(f, ")")?
+                    },
+                    HeapKind::List(ref items)    => {
+                        write!(f, "[")?;
+                        write_items(f, items)?;
+                        write!(f, "]")?
+                    },
                 }
             }
         }
@@ -89,3 +191,14 @@ impl Display for Value {
         Ok(())
     }
 }
+
+fn write_items(f: &mut Formatter, items: &[Value]) -> Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+
+    Ok(())
+}
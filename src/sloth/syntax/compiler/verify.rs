@@ -0,0 +1,315 @@
+use super::*;
+
+// what an instruction needs on the stack and what it leaves there
+fn stack_effect(op: OpCode) -> (usize, usize) {
+    match op {
+        OpCode::Add | OpCode::Sub | OpCode::Mul | OpCode::Rem | OpCode::Div | OpCode::Pow => (2, 1),
+
+        OpCode::Lt | OpCode::Gt | OpCode::LtEq | OpCode::GtEq | OpCode::Eq | OpCode::NotEq => (2, 1),
+
+        OpCode::Neg | OpCode::Convert(_) => (1, 1),
+
+        OpCode::Index => (2, 1),
+
+        OpCode::LoadConst(_) | OpCode::LoadLocal(_) => (0, 1),
+        OpCode::StoreLocal(_)                       => (1, 0),
+
+        OpCode::LoadGlobal(_)  => (0, 1),
+        OpCode::StoreGlobal(_) => (1, 0),
+
+        OpCode::MakeClosure(_) | OpCode::LoadUpvalue(_) => (0, 1),
+        OpCode::StoreUpvalue(_)                         => (1, 0),
+
+        OpCode::MakeTuple(n) | OpCode::MakeList(n) => (n as usize, 1),
+
+        OpCode::GetItem(_)     |
+        OpCode::ListRest(_)    |
+        OpCode::TestTuple(_)   |
+        OpCode::TestListLen(_) |
+        OpCode::TestListMin(_) |
+        OpCode::TestRange(..)  => (1, 1),
+
+        OpCode::BranchTrue(_) | OpCode::BranchFalse(_) => (1, 0),
+        OpCode::Jump(_)                                => (0, 0),
+
+        OpCode::Pop   => (1, 0),
+        OpCode::Print => (1, 0),
+
+        OpCode::Return       => (1, 1),
+        OpCode::Call(n)      => (n as usize + 1, 1),
+        OpCode::TailCall(n)  => (n as usize + 1, 1),
+        OpCode::MatchFail(_) => (0, 0),
+    }
+}
+
+// checks that running the block can't index out of its constants, locals or globals, jump
+// out of its code, find the stack in different shapes depending on how it got somewhere, or
+// run past its end, and the same for every function among its constants, marking what passes
+pub fn verify(block: &mut CompiledBlock) -> VerifyResult<()> {
+    let globals = block.globals.len();
+
+    verify_block(block, globals)
+}
+
+// functions reach the globals of the outermost block they're nested in
+fn verify_block(block: &mut CompiledBlock, globals: usize) -> VerifyResult<()> {
+    let error = |message: String, pc: usize| VerifyError::new(&message, pc, block.name.as_ref().map(|n| (**n).clone()));
+
+    let len = block.code.len();
+
+    if len == 0 {
+        return Err(error(String::from("no code to run"), 0))
+    }
+
+    for (pc, op) in block.code.iter().enumerate() {
+        match *op {
+            OpCode::LoadConst(i) | OpCode::MakeClosure(i) if i as usize >= block.consts.len() => return Err(error(format!("no constant {}", i), pc)),
+
+            OpCode::TestRange(lo, hi) if lo.max(hi) as usize >= block.consts.len() => return Err(error(format!("no constant {}", lo.max(hi)), pc)),
+
+            OpCode::LoadLocal(i) | OpCode::StoreLocal(i) if i as usize >= block.locals.len() => return Err(error(format!("no local {}", i), pc)),
+
+            OpCode::MatchFail(n) if n as usize > block.locals.len() => return Err(error(format!("no local {}", n - 1), pc)),
+
+            OpCode::LoadUpvalue(i) | OpCode::StoreUpvalue(i) if i as usize >= block.upvalues.len() => return Err(error(format!("no upvalue {}", i), pc)),
+
+            OpCode::LoadGlobal(i) | OpCode::StoreGlobal(i) if i as usize >= globals => return Err(error(format!("no global {}", i), pc)),
+
+            // a closure captures from this block's locals and upvalues, and a function that
+            // captures anything can only be reached through one
+            OpCode::LoadConst(i) | OpCode::MakeClosure(i) => {
+                let value    = block.consts[i as usize];
+                let function = match value.heap_kind() {
+                    Some(&HeapKind::Function(ref function)) => Some(function),
+                    _                                       => None,
+                };
+
+                let captures = function.map_or(&[][..], |function| &function.upvalues[..]);
+
+                match block.code[pc] {
+                    OpCode::LoadConst(_) if !captures.is_empty() => return Err(error(format!("loads {} without its upvalues", describe(value)), pc)),
+                    OpCode::MakeClosure(_) if function.is_none() => return Err(error(format!("can't close over {}", describe(value)), pc)),
+                    _                                            => (),
+                }
+
+                for capture in captures {
+                    let bound = if capture.local { block.locals.len() } else { block.upvalues.len() };
+
+                    if capture.index as usize >= bound {
+                        return Err(error(format!("{} captures {} from nowhere", describe(value), capture.name), pc))
+                    }
+                }
+            },
+
+            OpCode::BranchTrue(d) | OpCode::BranchFalse(d) | OpCode::Jump(d) => {
+                let target = pc as isize + d as isize;
+
+                if target < 0 || target as usize >= len {
+                    return Err(error(format!("jump to {} is outside the block", target), pc))
+                }
+            },
+
+            _ => (),
+        }
+    }
+
+    // walks every path through the block, remembering the depth each instruction was reached with
+    let mut depths = vec![None; len];
+    let mut work   = vec![(0, 0)];
+
+    while let Some((pc, depth)) = work.pop() {
+        if pc >= len {
+            return Err(error(String::from("runs past the end without returning"), pc - 1))
+        }
+
+        match depths[pc] {
+            Some(seen) if seen == depth => continue,
+            Some(seen)                  => return Err(error(format!("stack depth is {} on one path and {} on another", seen, depth), pc)),
+            None                        => depths[pc] = Some(depth),
+        }
+
+        let op = block.code[pc];
+        let (pops, pushes) = stack_effect(op);
+
+        if depth < pops {
+            return Err(error(format!("{} needs {} values, the stack has {}", op.name(), pops, depth), pc))
+        }
+
+        // leaving a frame, nothing but what's handed on may be left on its stack
+        match op {
+            OpCode::Return | OpCode::TailCall(_) if depth != pops => {
+                return Err(error(format!("{} needs exactly {} values, the stack has {}", op.name(), pops, depth), pc))
+            },
+            _ => (),
+        }
+
+        let after = depth - pops + pushes;
+
+        match op {
+            OpCode::Return | OpCode::TailCall(_) | OpCode::MatchFail(_) => (),
+
+            OpCode::Jump(d) => work.push(((pc as isize + d as isize) as usize, after)),
+
+            OpCode::BranchTrue(d) | OpCode::BranchFalse(d) => {
+                work.push(((pc as isize + d as isize) as usize, after));
+                work.push((pc + 1, after))
+            },
+
+            _ => work.push((pc + 1, after)),
+        }
+    }
+
+    for value in block.consts.iter() {
+        if let Value::HeapObject(p) = *value {
+            if let HeapKind::Function(ref mut function) = unsafe { &mut (*p).kind } {
+                verify_block(function, globals)?
+            }
+        }
+    }
+
+    block.verified = true;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn block(code: Vec<OpCode>, consts: Vec<Value>, locals: usize, globals: usize) -> CompiledBlock {
+        CompiledBlock {
+            code:   code.into_boxed_slice(),
+            consts: consts.into_boxed_slice(),
+            locals: vec![String::new(); locals].into_boxed_slice(),
+            lines:  LineTable::new(),
+            name:   None,
+            arms:   Box::new([]),
+            upvalues: Box::new([]),
+            globals:  vec![String::from("g"); globals].into_boxed_slice(),
+            verified: false,
+        }
+    }
+
+    // the message and pc the block is turned down with
+    fn rejection(mut block: CompiledBlock) -> (String, usize) {
+        match verify(&mut block) {
+            Ok(())   => panic!("verified {:?}", block.code),
+            Err(err) => {
+                assert!(!block.verified);
+
+                let VerifyErrorValue::Constant(ref message) = err.value;
+                (message.clone(), err.pc)
+            },
+        }
+    }
+
+    #[test]
+    fn accepts_balanced_block() {
+        let mut block = block(vec![
+            OpCode::LoadLocal(0),
+            OpCode::BranchFalse(3),
+            OpCode::LoadConst(0),
+            OpCode::Jump(2),
+            OpCode::LoadGlobal(0),
+            OpCode::Return,
+        ], vec![Value::Int(1)], 1, 1);
+
+        assert!(verify(&mut block).is_ok());
+        assert!(block.verified);
+    }
+
+    #[test]
+    fn rejects_empty_block() {
+        assert_eq!(rejection(block(vec![], vec![], 0, 0)), (String::from("no code to run"), 0));
+    }
+
+    #[test]
+    fn rejects_missing_constant() {
+        let code = vec![OpCode::LoadConst(1), OpCode::Return];
+
+        assert_eq!(rejection(block(code, vec![Value::Int(1)], 0, 0)), (String::from("no constant 1"), 0));
+    }
+
+    #[test]
+    fn rejects_missing_local() {
+        let code = vec![OpCode::LoadConst(0), OpCode::StoreLocal(2), OpCode::LoadConst(0), OpCode::Return];
+
+        assert_eq!(rejection(block(code, vec![Value::Int(1)], 2, 0)), (String::from("no local 2"), 1));
+    }
+
+    #[test]
+    fn rejects_missing_global() {
+        let code = vec![OpCode::LoadGlobal(1), OpCode::Return];
+
+        assert_eq!(rejection(block(code, vec![], 0, 1)), (String::from("no global 1"), 0));
+    }
+
+    #[test]
+    fn rejects_jump_outside_block() {
+        let code = vec![OpCode::LoadConst(0), OpCode::Jump(-2), OpCode::Return];
+
+        assert_eq!(rejection(block(code, vec![Value::Int(1)], 0, 0)), (String::from("jump to -1 is outside the block"), 1));
+    }
+
+    #[test]
+    fn rejects_depth_mismatch() {
+        // the fall through reaches the pop with one more value than the branch does
+        let code = vec![
+            OpCode::LoadConst(0),
+            OpCode::LoadConst(1),
+            OpCode::BranchTrue(2),
+            OpCode::LoadConst(0),
+            OpCode::Pop,
+            OpCode::Return,
+        ];
+
+        let (message, pc) = rejection(block(code, vec![Value::Int(1), Value::Bool(true)], 0, 0));
+
+        assert_eq!(pc, 4);
+        assert!(message.starts_with("stack depth is"), "{}", message);
+    }
+
+    #[test]
+    fn rejects_underflow() {
+        let code = vec![OpCode::Print, OpCode::LoadConst(0), OpCode::Return];
+
+        assert_eq!(rejection(block(code, vec![Value::Null], 0, 0)), (String::from("print needs 1 values, the stack has 0"), 0));
+    }
+
+    #[test]
+    fn rejects_missing_return() {
+        let code = vec![OpCode::LoadConst(0), OpCode::Print];
+
+        assert_eq!(rejection(block(code, vec![Value::Null], 0, 0)), (String::from("runs past the end without returning"), 1));
+    }
+
+    #[test]
+    fn rejects_values_left_behind() {
+        let code = vec![OpCode::LoadConst(0), OpCode::LoadConst(0), OpCode::Return];
+
+        assert_eq!(rejection(block(code, vec![Value::Null], 0, 0)), (String::from("return needs exactly 1 values, the stack has 2"), 2));
+
+        // a tail call of one argument hands on the callee and the argument, nothing more
+        let code = vec![OpCode::LoadConst(0), OpCode::LoadConst(0), OpCode::LoadConst(0), OpCode::LoadConst(0), OpCode::TailCall(1)];
+
+        assert_eq!(rejection(block(code, vec![Value::Null], 0, 0)), (String::from("tail_call needs exactly 2 values, the stack has 4"), 4));
+    }
+
+    #[test]
+    fn rejects_bad_nested_function() {
+        let mut vm = VirtualMachine::new();
+
+        let mut inner = block(vec![OpCode::LoadLocal(0), OpCode::Return], vec![], 0, 0);
+        inner.name = Some(Rc::new(String::from("f")));
+
+        let function  = vm.allocate_object(HeapKind::Function(inner));
+        let mut outer = block(vec![OpCode::LoadConst(0), OpCode::Return], vec![function], 0, 0);
+
+        match verify(&mut outer) {
+            Err(err) => assert_eq!(err.function, Some(String::from("f"))),
+            Ok(())   => panic!("verified a function loading a missing local"),
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::mem;
-use std::ptr;
+use std::rc::Rc;
 
 use super::*;
 
@@ -8,6 +10,8 @@ pub struct CallInfo {
     locals: Box<[Value]>,
     pc:     usize,
     func:   *const CompiledBlock,
+    // the function object `func` belongs to, null for the block `execute` was given
+    callee: Value,
 }
 
 
@@ -21,6 +25,7 @@ pub enum OpCode {
     Pow,
 
     Neg,
+    Convert(Width),
 
     Lt,
     Gt,
@@ -32,7 +37,20 @@ pub enum OpCode {
     LoadConst(u16),
     LoadLocal(u16),
     StoreLocal(u16),
+    LoadGlobal(u16),
+    StoreGlobal(u16),
     
+    MakeTuple(u16),
+    MakeList(u16),
+    GetItem(u16),
+    ListRest(u16),
+    Index,
+
+    TestTuple(u16),
+    TestListLen(u16),
+    TestListMin(u16),
+    TestRange(u16, u16),
+
     BranchTrue(i16),
     BranchFalse(i16),
     Jump(i16),
@@ -40,14 +58,23 @@ pub enum OpCode {
     Pop,
     Return,
     Call(u8),
+    TailCall(u8),
+    MakeClosure(u16),
+    LoadUpvalue(u16),
+    StoreUpvalue(u16),
+    MatchFail(u8),
     Print,
 }
 
+// clones share one heap, which is how the compiler's constants end up where the machine collects
 #[derive(Debug, Clone)]
 pub struct VirtualMachine {
     pub value_stack: Vec<Value>,
     pub call_stack:  Vec<CallInfo>,
-    pub next_object: *mut HeapObject,
+    pub globals:     Vec<Value>,
+    pub heap:        Rc<RefCell<Heap>>,
+    // upvalues still pointing into a running frame, closed when it returns
+    pub open_upvalues: Vec<Value>,
 }
 
 impl VirtualMachine {
@@ -55,32 +82,81 @@ impl VirtualMachine {
         VirtualMachine {
             value_stack: Vec::new(),
             call_stack:  Vec::new(),
-            next_object: ptr::null_mut(),
+            globals:     Vec::new(),
+            heap:        Rc::new(RefCell::new(Heap::new())),
+            open_upvalues: Vec::new(),
         }
     }
 
-    pub fn execute(&mut self, initial_block: *const CompiledBlock) {
+    // `globals` outlive the call, so later blocks see what earlier ones defined
+    pub fn execute(&mut self, initial_block: *const CompiledBlock) -> RunResult<Value> {
         let mut pc = 0;
         let mut func = unsafe {&*initial_block};
+
+        if !func.verified {
+            return Err(RuntimeError::new("refusing to run unverified bytecode"))
+        }
+
+        let mut callee = Value::Null;
+
+        if self.globals.len() < func.globals.len() {
+            self.globals.resize(func.globals.len(), Value::Null)
+        }
+
         let mut locals = vec![Value::Null; func.locals.len()].into_boxed_slice();
 
-        macro_rules! match_binop {
-            ($($pat:pat => $block:block)+) => {{
-                let _a = self.value_stack.pop().unwrap();
-                let _b = self.value_stack.pop().unwrap();
+        let base       = self.call_stack.len();
+        let stack_base = self.value_stack.len();
+
+        // the verifier rules running short of values out, this is what's left if it's wrong
+        macro_rules! pop {
+            () => {
+                match self.value_stack.pop() {
+                    Some(value) => value,
+                    None        => break Err(RuntimeError::new("stack underflow")),
+                }
+            }
+        }
+
+        // where the top `n` values start
+        macro_rules! below {
+            ($n:expr) => {
+                match self.value_stack.len().checked_sub($n) {
+                    Some(at) => at,
+                    None     => break Err(RuntimeError::new("stack underflow")),
+                }
+            }
+        }
+
+        macro_rules! arith {
+            ($op:expr) => {{
+                let a = pop!();
+                let b = pop!();
+
+                match b.arith(&$op, a) {
+                    Ok(value) => self.value_stack.push(value),
+                    Err(err)  => break Err(err.with_operands(vec![b, a])),
+                }
+            }}
+        }
 
-                let _result = match (_b, _a) {
-                    $($pat => $block)+,
-                    _ => panic!("unexpected operand"),
-                };
+        // a comparison involving NaN is false, like it is in Rust
+        macro_rules! order {
+            ($test:expr) => {{
+                let a = pop!();
+                let b = pop!();
 
-                self.value_stack.push(_result);
+                match b.order(a) {
+                    Ok(ordering) => self.value_stack.push(Value::Bool(ordering.map_or(false, $test))),
+                    Err(err)     => break Err(err.with_operands(vec![b, a])),
+                }
             }}
         }
 
-        loop {
+        // a failing instruction breaks out before `pc` moves past it
+        let result = loop {
             if pc >= func.code.len() {
-                break
+                break Ok(())
             }
 
             let op = func.code[pc];
@@ -88,10 +164,13 @@ impl VirtualMachine {
             match op {
                 OpCode::LoadConst(i)  => { self.value_stack.push(func.consts[i as usize]); },
                 OpCode::LoadLocal(i)  => { self.value_stack.push(locals[i as usize]); },
-                OpCode::StoreLocal(i) => locals[i as usize] = self.value_stack.pop().unwrap(),
+                OpCode::StoreLocal(i) => locals[i as usize] = pop!(),
+
+                OpCode::LoadGlobal(i)  => { self.value_stack.push(self.globals[i as usize]); },
+                OpCode::StoreGlobal(i) => self.globals[i as usize] = pop!(),
 
                 OpCode::BranchTrue(d) => {
-                    if self.value_stack.pop().unwrap().truthy() {
+                    if pop!().truthy() {
                         pc = pc.wrapping_add((d as isize) as usize)
                     } else {
                         pc = pc.wrapping_add(1)
@@ -101,7 +180,7 @@ impl VirtualMachine {
                 }
 
                 OpCode::BranchFalse(d) => {
-                    if !self.value_stack.pop().unwrap().truthy() {
+                    if !pop!().truthy() {
                         pc = pc.wrapping_add((d as isize) as usize)
                     } else {
                         pc = pc.wrapping_add(1)
@@ -115,180 +194,170 @@ impl VirtualMachine {
                     continue
                 }
 
-                OpCode::Pop => { self.value_stack.pop().unwrap(); },
-                
-                OpCode::Add => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
-
-                    let result = match (b, a) {
-                        (Value::Int(a), Value::Int(b))     => Value::Int(a + b),
-                        (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
-                        (Value::Float(a), Value::Int(b))   => Value::Float(a + b as f64),
-                        _ => panic!("unexpected operand"),
-                    };
+                OpCode::Pop => { pop!(); },
 
-                    self.value_stack.push(result)
-                }
+                OpCode::MakeTuple(n) => {
+                    let at    = below!(n as usize);
+                    let items = self.value_stack.split_off(at);
+                    let tuple = self.allocate_object(HeapKind::Tuple(items));
 
-                OpCode::Sub => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
+                    self.value_stack.push(tuple);
+                    self.collect_if_due(&locals, func, callee)
+                }
 
-                    let result = match (b, a) {
-                        (Value::Int(a), Value::Int(b))     => Value::Int(a - b),
-                        (Value::Float(a), Value::Float(b)) => Value::Float(a - b),
-                        (Value::Float(a), Value::Int(b))   => Value::Float(a - b as f64),
-                        _ => panic!("unexpected operand"),
-                    };
+                OpCode::MakeList(n) => {
+                    let at    = below!(n as usize);
+                    let items = self.value_stack.split_off(at);
+                    let list  = self.allocate_object(HeapKind::List(items));
 
-                    self.value_stack.push(result)
+                    self.value_stack.push(list);
+                    self.collect_if_due(&locals, func, callee)
                 }
 
-                OpCode::Rem => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
+                OpCode::GetItem(i) => {
+                    let value = pop!();
 
-                    let result = match (b, a) {
-                        (Value::Int(a), Value::Int(b))     => Value::Int(a % b),
-                        (Value::Float(a), Value::Float(b)) => Value::Float(a % b),
-                        _ => panic!("unexpected operand"),
+                    let item = match value.heap_kind() {
+                        Some(&HeapKind::Tuple(ref items)) |
+                        Some(&HeapKind::List(ref items)) if (i as usize) < items.len() => items[i as usize],
+                        _ => break Err(RuntimeError::new(&format!("no item {} in {}", i, value)).with_operands(vec![value])),
                     };
 
-                    self.value_stack.push(result)
+                    self.value_stack.push(item)
                 }
 
-                OpCode::Div => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
+                // `xs[i]`, with the index worked out at runtime
+                OpCode::Index => {
+                    let index = pop!();
+                    let value = pop!();
 
-                    let result = match (b, a) {
-                        (Value::Int(a), Value::Int(b))     => Value::Int(a / b),
-                        (Value::Float(a), Value::Float(b)) => Value::Float(a / b),
-                        (Value::Float(a), Value::Int(b))   => Value::Float(a / b as f64),
-                        _ => panic!("unexpected operand"),
+                    let item = match (value.heap_kind(), index.as_index()) {
+                        (Some(&HeapKind::List(ref items)), Some(i)) if i < items.len() => items[i],
+                        _ => break Err(RuntimeError::new(&format!("index {} is out of range for {}", index, value)).with_operands(vec![value, index])),
                     };
 
-                    self.value_stack.push(result)
+                    self.value_stack.push(item)
                 }
 
-                OpCode::Pow => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
+                OpCode::ListRest(i) => {
+                    let value = pop!();
 
-                    let result = match (b, a) {
-                        (Value::Float(a), Value::Float(b)) => Value::Float(a.powf(b)),
-                        (Value::Float(a), Value::Int(b))   => Value::Float(a.powf(b as f64)),
-                        _ => panic!("unexpected operand"),
+                    let rest = match value.heap_kind() {
+                        Some(&HeapKind::List(ref items)) if (i as usize) <= items.len() => items[i as usize ..].to_vec(),
+                        _ => break Err(RuntimeError::new(&format!("no items from {} in {}", i, value)).with_operands(vec![value])),
                     };
 
-                    self.value_stack.push(result)
-                }
+                    let list = self.allocate_object(HeapKind::List(rest));
 
-                OpCode::Lt   => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
+                    self.value_stack.push(list);
+                    self.collect_if_due(&locals, func, callee)
+                }
 
-                    let result = match (b, a) {
-                        (Value::Int(a), Value::Int(b))     => Value::Bool(a < b),
-                        (Value::Float(a), Value::Float(b)) => Value::Bool(a < b),
-                        (Value::Float(a), Value::Int(b))   => Value::Bool(a < b as f64),
-                        (Value::Int(a), Value::Float(b))   => Value::Bool(a < b as i64),
-                        _ => panic!("unexpected operand"),
+                OpCode::TestTuple(n) => {
+                    let result = match pop!().heap_kind() {
+                        Some(&HeapKind::Tuple(ref items)) => items.len() == n as usize,
+                        _                                 => false,
                     };
 
-                    self.value_stack.push(result)
+                    self.value_stack.push(Value::Bool(result))
                 }
 
-                OpCode::Gt   => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
-
-                    let result = match (b, a) {
-                        (Value::Int(a), Value::Int(b))     => Value::Bool(a > b),
-                        (Value::Float(a), Value::Float(b)) => Value::Bool(a > b),
-                        (Value::Float(a), Value::Int(b))   => Value::Bool(a > b as f64),
-                        (Value::Int(a), Value::Float(b))   => Value::Bool(a > b as i64),
-                        _ => panic!("unexpected operand"),
+                OpCode::TestListLen(n) => {
+                    let result = match pop!().heap_kind() {
+                        Some(&HeapKind::List(ref items)) => items.len() == n as usize,
+                        _                                => false,
                     };
 
-                    self.value_stack.push(result)
+                    self.value_stack.push(Value::Bool(result))
                 }
 
-                OpCode::LtEq => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
-
-                    let result = match (b, a) {
-                        (Value::Int(a), Value::Int(b))     => Value::Bool(a <= b),
-                        (Value::Float(a), Value::Float(b)) => Value::Bool(a <= b),
-                        (Value::Float(a), Value::Int(b))   => Value::Bool(a <= b as f64),
-                        (Value::Int(a), Value::Float(b))   => Value::Bool(a <= b as i64),
-                        _ => panic!("unexpected operand"),
+                OpCode::TestListMin(n) => {
+                    let result = match pop!().heap_kind() {
+                        Some(&HeapKind::List(ref items)) => items.len() >= n as usize,
+                        _                                => false,
                     };
 
-                    self.value_stack.push(result)
+                    self.value_stack.push(Value::Bool(result))
                 }
 
-                OpCode::GtEq => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
+                OpCode::TestRange(lo, hi) => {
+                    let value = pop!();
 
-                    let result = match (b, a) {
-                        (Value::Int(a), Value::Int(b))     => Value::Bool(a >= b),
-                        (Value::Float(a), Value::Float(b)) => Value::Bool(a >= b),
-                        (Value::Float(a), Value::Int(b))   => Value::Bool(a >= b as f64),
-                        (Value::Int(a), Value::Float(b))   => Value::Bool(a >= b as i64),
-                        _ => panic!("unexpected operand"),
-                    };
+                    let above = func.consts[lo as usize].compare(value).is_some_and(|o| o != Ordering::Greater);
+                    let below = value.compare(func.consts[hi as usize]).is_some_and(|o| o != Ordering::Greater);
 
-                    self.value_stack.push(result)
+                    self.value_stack.push(Value::Bool(above && below))
                 }
+                
+                OpCode::Add => arith!(Operand::Add),
+                OpCode::Sub => arith!(Operand::Sub),
+                OpCode::Mul => arith!(Operand::Mul),
+                OpCode::Rem => arith!(Operand::Mod),
+                OpCode::Div => arith!(Operand::Div),
+                OpCode::Pow => arith!(Operand::Pow),
+
+                OpCode::Lt   => order!(|o| o == Ordering::Less),
+                OpCode::Gt   => order!(|o| o == Ordering::Greater),
+                OpCode::LtEq => order!(|o| o != Ordering::Greater),
+                OpCode::GtEq => order!(|o| o != Ordering::Less),
 
                 OpCode::Eq => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
+                    let a = pop!();
+                    let b = pop!();
 
-                    self.value_stack.push(Value::Bool(a == b));
+                    self.value_stack.push(Value::Bool(b.equals(a)));
                 }
 
                 OpCode::NotEq => {
-                    let a = self.value_stack.pop().unwrap();
-                    let b = self.value_stack.pop().unwrap();
+                    let a = pop!();
+                    let b = pop!();
 
-                    self.value_stack.push(Value::Bool(a != b));
+                    self.value_stack.push(Value::Bool(!b.equals(a)));
                 }
 
                 OpCode::Neg => {
-                    if let Value::Int(n) = self.value_stack.pop().unwrap() {
-                        self.value_stack.push(Value::Int(-n));
-                    } else if let Value::Float(n) = self.value_stack.pop().unwrap() {
-                        self.value_stack.push(Value::Float(-n));
-                    } else {
-                        panic!("unexpected operand type");
+                    let value = pop!();
+
+                    match value.negate() {
+                        Ok(value) => self.value_stack.push(value),
+                        Err(err)  => break Err(err.with_operands(vec![value])),
                     }
-                }
+                },
+
+                OpCode::Convert(width) => {
+                    let value = pop!();
 
-                OpCode::Call(args) => {                    
+                    match value.convert(width) {
+                        Ok(value) => self.value_stack.push(value),
+                        Err(err)  => break Err(err.with_operands(vec![value])),
+                    }
+                },
+
+                // a tail call runs in the frame of the function making it, which is done with it
+                OpCode::Call(args) | OpCode::TailCall(args) => {
                     let args = args as usize;
 
-                    let func_i = self.value_stack.len() - args - 1;
+                    let func_i = below!(args + 1);
                     let func_v = self.value_stack[func_i];
 
-                    let old_func = func;
-
-                    func = if let Value::HeapObject(p) = func_v {
-                        let obj = unsafe {&*p};
+                    let operands = self.value_stack[func_i ..].to_vec();
 
-                        if let HeapKind::Function(ref func) = obj.kind {
-                            func
-                        } else {
-                            panic!("calling non-func: {:#?}", func_v)
-                        }
-                    } else {
-                        panic!("calling non-func: {:#?}", func_v)
+                    let block = match callable(func_v) {
+                        Some(block) => block,
+                        None        => break Err(RuntimeError::new(&format!("can't call {}", func_v)).with_operands(operands)),
                     };
 
+                    if !block.verified {
+                        break Err(RuntimeError::new("refusing to call unverified bytecode").with_operands(operands))
+                    }
+
+                    if args > block.locals.len() {
+                        break Err(RuntimeError::new(&format!("too many arguments: {}", args)).with_operands(operands))
+                    }
+
+                    let old_func   = mem::replace(&mut func, block);
+                    let old_callee = mem::replace(&mut callee, func_v);
+
                     let mut new_locals = vec![Value::Null; func.locals.len()].into_boxed_slice();
 
                     for i in 0 .. args {
@@ -300,38 +369,309 @@ impl VirtualMachine {
                     }
 
                     let old_locals = mem::replace(&mut locals, new_locals);
-                    
-                    self.call_stack.push(CallInfo {
-                        pc,
-                        locals: old_locals,
-                        func: old_func,
-                    });
+
+                    if let OpCode::TailCall(_) = op {
+                        self.close_upvalues(&old_locals);
+                    } else {
+                        self.call_stack.push(CallInfo {
+                            pc,
+                            locals: old_locals,
+                            func: old_func,
+                            callee: old_callee,
+                        });
+                    }
                     
                     pc = 0;
                     
                     continue
                 }
                 
+                OpCode::MakeClosure(i) => {
+                    let function = func.consts[i as usize];
+
+                    let captures = match callable(function) {
+                        Some(block) => &block.upvalues,
+                        None        => break Err(RuntimeError::new(&format!("can't close over {}", function)).with_operands(vec![function])),
+                    };
+
+                    let mut upvalues = Vec::new();
+
+                    for capture in captures.iter() {
+                        if capture.local {
+                            let slot = &mut locals[capture.index as usize] as *mut Value;
+                            upvalues.push(self.open_upvalue(slot))
+                        } else {
+                            match captured(callee, capture.index) {
+                                Some(upvalue) => upvalues.push(upvalue),
+                                None          => break,
+                            }
+                        }
+                    }
+
+                    if upvalues.len() != captures.len() {
+                        break Err(RuntimeError::new(&format!("nothing to capture {} from", function)).with_operands(vec![function]))
+                    }
+
+                    let closure = self.allocate_object(HeapKind::Closure(function, upvalues));
+
+                    self.value_stack.push(closure);
+                    self.collect_if_due(&locals, func, callee)
+                }
+
+                OpCode::LoadUpvalue(i) => match captured(callee, i).and_then(upvalue_cell) {
+                    Some(&mut Upvalue::Open(slot))   => self.value_stack.push(unsafe { *slot }),
+                    Some(&mut Upvalue::Closed(value)) => self.value_stack.push(value),
+                    None                              => break Err(RuntimeError::new(&format!("no upvalue {}", i))),
+                },
+
+                OpCode::StoreUpvalue(i) => {
+                    let value = pop!();
+
+                    match captured(callee, i).and_then(upvalue_cell) {
+                        Some(cell) => match *cell {
+                            Upvalue::Open(slot) => unsafe { *slot = value },
+                            Upvalue::Closed(_)  => *cell = Upvalue::Closed(value),
+                        },
+                        None => break Err(RuntimeError::new(&format!("no upvalue {}", i)).with_operands(vec![value])),
+                    }
+                }
+
+                // the result stays on top of the value stack for the caller
+                OpCode::Return => {
+                    if self.call_stack.len() == base {
+                        break Ok(())
+                    }
+
+                    self.close_upvalues(&locals);
+
+                    let caller = self.call_stack.pop().unwrap();
+
+                    pc     = caller.pc;
+                    func   = unsafe {&*caller.func};
+                    callee = caller.callee;
+                    locals = caller.locals;
+                }
+
+                OpCode::MatchFail(args) => {
+                    let operands = locals[.. args as usize].to_vec();
+                    let args     = operands.iter().map(|a| format!("{}", a)).collect::<Vec<String>>();
+
+                    break Err(RuntimeError::new(&format!("no arm matches: {}", args.join(", "))).with_operands(operands))
+                }
+
                 OpCode::Print => {
-                    println!("{}", self.value_stack.pop().unwrap());
+                    println!("{}", pop!());
                 }
-                
-                _ => (),
             }
 
             pc = pc.wrapping_add(1)
+        };
+
+        let result = result.map_err(|mut err| {
+            if err.op.is_none() {
+                err.op       = Some(func.code[pc]);
+                err.position = func.lines.position(pc);
+                err.trace    = self.trace(base, func, pc, callee);
+            }
+
+            err
+        });
+
+        // every frame this call ran is done, so nothing can point into them any more
+        self.close_upvalues(&locals);
+
+        let frames = self.call_stack.drain(base ..).collect::<Vec<_>>();
+
+        for frame in frames {
+            self.close_upvalues(&frame.locals)
+        }
+
+        match result {
+            Ok(())   => Ok(self.value_stack.pop().unwrap_or(Value::Null)),
+            Err(err) => {
+                self.value_stack.truncate(stack_base);
+                Err(err)
+            },
         }
     }
 
+    // every frame from the one that failed out to the one `execute` started with
+    fn trace(&self, base: usize, func: &CompiledBlock, pc: usize, callee: Value) -> Vec<TraceFrame> {
+        let frame = |func: &CompiledBlock, pc: usize, callee: Value| TraceFrame {
+            function: match (&func.name, callee) {
+                (&Some(ref name), _) => Some((**name).clone()),
+                (&None, Value::Null) => None,
+                (&None, _)           => Some(String::from("<anonymous>")),
+            },
+            arm:      func.arm_at(pc),
+            position: func.lines.position(pc),
+        };
+
+        let mut trace = vec![frame(func, pc, callee)];
+
+        for info in self.call_stack[base ..].iter().rev() {
+            trace.push(frame(unsafe {&*info.func}, info.pc, info.callee))
+        }
+
+        trace
+    }
+
+    // frames capturing one variable share its upvalue
+    fn open_upvalue(&mut self, slot: *mut Value) -> Value {
+        for &upvalue in self.open_upvalues.iter() {
+            if let Some(&mut Upvalue::Open(open)) = upvalue_cell(upvalue) {
+                if open == slot {
+                    return upvalue
+                }
+            }
+        }
+
+        let upvalue = self.allocate_object(HeapKind::Upvalue(Upvalue::Open(slot)));
+        self.open_upvalues.push(upvalue);
+
+        upvalue
+    }
+
+    // moves the values of upvalues pointing into `locals` into the upvalues themselves
+    fn close_upvalues(&mut self, locals: &[Value]) {
+        let range = locals.as_ptr_range();
+
+        self.open_upvalues.retain(|&upvalue| match upvalue_cell(upvalue) {
+            Some(cell) => match *cell {
+                Upvalue::Open(slot) if range.contains(&(slot as *const Value)) => {
+                    *cell = Upvalue::Closed(unsafe { *slot });
+                    false
+                },
+                _ => true,
+            },
+            None => false,
+        })
+    }
+
     pub fn allocate_object(&mut self, kind: HeapKind) -> Value {
-        let obj = Box::into_raw(Box::new(HeapObject {
-            next: self.next_object,
-            marked: false,
-            kind,
-        }));
+        self.heap.borrow_mut().allocate(kind)
+    }
+
+    pub fn set_gc_threshold(&mut self, threshold: usize) {
+        self.heap.borrow_mut().set_threshold(threshold)
+    }
+
+    // only called from `execute` with everything pushed, as the running frame's locals
+    // and constants are roots alongside those of every frame waiting on it
+    fn collect_if_due(&mut self, locals: &[Value], func: &CompiledBlock, callee: Value) {
+        if !self.heap.borrow().should_collect() {
+            return
+        }
+
+        self.collect(locals, func, callee)
+    }
+
+    pub fn collect(&mut self, locals: &[Value], func: &CompiledBlock, callee: Value) {
+        let mut roots = self.value_stack.clone();
+
+        roots.extend(self.globals.iter().cloned());
+        roots.extend(locals.iter().cloned());
+        roots.extend(func.consts.iter().cloned());
+        roots.push(callee);
+        roots.extend(self.open_upvalues.iter().cloned());
+
+        for frame in self.call_stack.iter() {
+            roots.extend(frame.locals.iter().cloned());
+            roots.extend(unsafe { (*frame.func).consts.iter().cloned() });
+            roots.push(frame.callee);
+        }
+
+        mark(&roots);
+
+        self.heap.borrow_mut().sweep()
+    }
+}
+
+// the code a callable value runs, whether it's a bare function or a closure over one
+fn callable(value: Value) -> Option<&'static CompiledBlock> {
+    match value {
+        Value::HeapObject(p) => match unsafe { &(*p).kind } {
+            &HeapKind::Function(ref block)  => Some(block),
+            &HeapKind::Closure(function, _) => callable(function),
+            _                               => None,
+        },
+        _ => None,
+    }
+}
+
+// the `i`th upvalue of a closure
+fn captured(closure: Value, i: u16) -> Option<Value> {
+    match closure.heap_kind() {
+        Some(&HeapKind::Closure(_, ref upvalues)) => upvalues.get(i as usize).cloned(),
+        _                                         => None,
+    }
+}
+
+fn upvalue_cell(upvalue: Value) -> Option<&'static mut Upvalue> {
+    match upvalue {
+        Value::HeapObject(p) => match unsafe { &mut (*p).kind } {
+            &mut HeapKind::Upvalue(ref mut cell) => Some(cell),
+            _                                    => None,
+        },
+        _ => None,
+    }
+}
 
-        self.next_object = obj;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::testing::run;
+
+    fn failure(source: &str) -> String {
+        match run(source) {
+            Ok(value) => panic!("ran to {}", value),
+            Err(err)  => format!("{}", err),
+        }
+    }
+
+    #[test]
+    fn index_takes_ints_of_every_width() {
+        for t in &["i08", "i16", "i32", "i64", "i128", "u08", "u16", "u32", "u64", "u128"] {
+            let source = format!("xs := [10, 20, 30]\ni: {} = 1\nxs[i]\n", t);
+            assert_eq!(run(&source).unwrap(), Value::Int(20), "{}", t);
+        }
+
+        assert_eq!(run("xs := [10, 20, 30]\nxs[2]\n").unwrap(), Value::Int(30));
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        assert_eq!(failure("xs := [10, 20]\nxs[2]\n"), "index 2 is out of range for [10, 20]");
+        assert_eq!(failure("xs := [10, 20]\ni: i08 = -1\nxs[i]\n"), "index -1 is out of range for [10, 20]");
+    }
+
+    #[test]
+    fn tail_calls_run_in_constant_stack() {
+        let count = "count := {\n  |(0, acc)| acc\n  |(n, acc)| count (n - 1, acc + 1)\n}\ncount (1000000, 0)\n";
+        assert_eq!(run(count).unwrap(), Value::Int(1000000));
+
+        // a million calls deep, the loop still sits in one frame above the top level
+        match run("loop := {\n  |0| 1 / 0\n  |n| loop n - 1\n}\nloop 1000000\n") {
+            Ok(value) => panic!("ran to {}", value),
+            Err(err)  => assert_eq!(err.trace.len(), 2),
+        }
+    }
+
+    #[test]
+    fn mutual_tail_calls_run_in_constant_stack() {
+        let source = "is_even := {\n  |0| true\n  |n| is_odd n - 1\n}\nis_odd := {\n  |0| false\n  |n| is_even n - 1\n}\nis_even 1000001\n";
+        assert_eq!(run(source).unwrap(), Value::Bool(false));
+
+        match run("ping := {\n  |0| 1 / 0\n  |n| pong n - 1\n}\npong := {\n  |n| ping n\n}\nping 1000000\n") {
+            Ok(value) => panic!("ran to {}", value),
+            Err(err)  => assert_eq!(err.trace.len(), 2),
+        }
+    }
 
-        Value::HeapObject(obj)
+    #[test]
+    fn numbers_keep_their_declared_width() {
+        assert_eq!(run("a: u08 = 200\na + 55\n").unwrap(), Value::U08(255));
+        assert_eq!(failure("a: u08 = 255\na + 1\n"), "overflow: 255 + 1 doesn't fit in u08");
+        assert_eq!(failure("a: i08 = -128\na - 1\n"), "overflow: -128 - 1 doesn't fit in i08");
     }
 }
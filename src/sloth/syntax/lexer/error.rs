@@ -0,0 +1,31 @@
+use super::*;
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub enum LexErrorValue {
+    Constant(String),
+}
+
+// a malformed token, lexing carries on after it
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub value:    LexErrorValue,
+    pub position: TokenPosition,
+}
+
+impl LexError {
+    pub fn new(value: &str, position: TokenPosition) -> LexError {
+        LexError {
+            value: LexErrorValue::Constant(value.to_owned()),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            LexErrorValue::Constant(ref s) => write!(f, "{}: {}", self.position, s),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::VecDeque;
+use std::mem;
+
+use super::*;
+
+// the offside rule between the lexer and the parser: a line indented further than an arm
+// header ending the line before it opens a block, an `Indent`, and the block is closed by
+// a `Dedent` in front of the first line indented less than it. lines indented further
+// without following a header only continue the line above inside brackets
+pub struct Layout<I: Iterator<Item = LexResult<Token>>> {
+    tokens: I,
+    // the widths of the open blocks, of the headers opening them and the brackets open
+    // around them, innermost last
+    blocks: Vec<(usize, usize, usize)>,
+    // brackets open where the line being read starts
+    depth:  usize,
+    // tabs or spaces, whichever the file is indented with first
+    indent: Option<char>,
+    // the width of the line being read, and that of the line before it if it was a header
+    width:  usize,
+    header: Option<usize>,
+    line_start: bool,
+    after_bar:  bool,
+    position:   TokenPosition,
+    span:       Span,
+    // comments attached to indentation, passed on to the token it comes before
+    trivia: Vec<Token>,
+    queue:  VecDeque<LexResult<Token>>,
+}
+
+impl<I: Iterator<Item = LexResult<Token>>> Layout<I> {
+    pub fn new(tokens: I) -> Layout<I> {
+        Layout {
+            tokens,
+            blocks: Vec::new(),
+            depth:  0,
+            indent: None,
+            width:  0,
+            header: None,
+            line_start: true,
+            after_bar:  false,
+            position:   TokenPosition::default(),
+            span:       Span::default(),
+            trivia: Vec::new(),
+            queue:  VecDeque::new(),
+        }
+    }
+
+    // the width of a line's indentation, which has to be all tabs or all spaces like
+    // every other line's
+    fn measure(&mut self, indent: &Token) -> LexResult<usize> {
+        for c in indent.content().chars() {
+            match self.indent {
+                Some(kind) if kind != c => return Err(LexError::new("indentation mixes tabs and spaces", indent.position)),
+                Some(_)                 => (),
+                None                    => self.indent = Some(c),
+            }
+        }
+
+        Ok(indent.content().len())
+    }
+
+    // queues the first token of a line behind the blocks it opens or closes, which take
+    // up no room in front of it
+    fn begin_line(&mut self, position: TokenPosition, span: Span) {
+        let span = Span::new(span.file, span.start, span.start);
+
+        match self.header {
+            Some(header) if self.width > header => {
+                self.blocks.push((self.width, header, self.depth));
+                self.queue.push_back(Ok(Token::new(TokenType::Indent, position, span, String::new())))
+            },
+
+            _ => {
+                let mut closed = None;
+
+                while self.blocks.last().is_some_and(|&(block, _, _)| block > self.width) {
+                    closed = self.blocks.pop();
+                    self.queue.push_back(Ok(Token::new(TokenType::Dedent, position, span, String::new())))
+                }
+
+                // closing blocks goes back to the block around them, or to the header of the
+                // outermost one closed, which can sit further in as part of a longer line
+                let (enclosing, depth) = self.blocks.last().map_or((0, 0), |&(block, _, depth)| (block, depth));
+
+                match closed {
+                    Some((_, header, _)) if self.width != enclosing && self.width != header => {
+                        self.queue.push_back(Err(LexError::new("dedent doesn't match any enclosing block", position)))
+                    },
+
+                    None if self.width > enclosing && self.depth == depth => {
+                        self.queue.push_back(Err(LexError::new("indented line doesn't follow an arm header", position)))
+                    },
+
+                    _ => (),
+                }
+            },
+        }
+
+        self.header     = None;
+        self.line_start = false
+    }
+
+    fn feed(&mut self, token: Token) {
+        match token.token_type {
+            TokenType::Indent => {
+                self.trivia.extend(token.trivia().iter().cloned());
+
+                match self.measure(&token) {
+                    Ok(width) => self.width = width,
+                    Err(err)  => {
+                        self.width = token.content().len();
+                        self.queue.push_back(Err(err))
+                    },
+                }
+            },
+
+            // blank lines don't count, an arm header's block can start after some
+            TokenType::EOL => {
+                if !self.line_start {
+                    self.header = if self.after_bar { Some(self.width) } else { None };
+                }
+
+                self.width      = 0;
+                self.line_start = true;
+                self.queue.push_back(Ok(token))
+            },
+
+            _ => {
+                let mut token = token;
+
+                if self.line_start {
+                    self.begin_line(token.position, token.span)
+                }
+
+                if !self.trivia.is_empty() {
+                    let mut trivia = mem::take(&mut self.trivia);
+
+                    trivia.extend(token.trivia().iter().cloned());
+                    token.set_trivia(trivia)
+                }
+
+                if token.token_type == TokenType::Symbol {
+                    match token.content().as_str() {
+                        "(" | "[" | "{" => self.depth += 1,
+                        ")" | "]" | "}" => self.depth = self.depth.saturating_sub(1),
+                        _               => (),
+                    }
+                }
+
+                self.after_bar = token.token_type == TokenType::Symbol && token.content() == "|";
+                self.position  = token.position;
+                self.span      = Span::new(token.span.file, token.span.end, token.span.end);
+                self.queue.push_back(Ok(token))
+            },
+        }
+    }
+}
+
+impl<I: Iterator<Item = LexResult<Token>>> Iterator for Layout<I> {
+    type Item = LexResult<Token>;
+
+    // every block still open is closed at the end of the source
+    fn next(&mut self) -> Option<LexResult<Token>> {
+        while self.queue.is_empty() {
+            match self.tokens.next() {
+                Some(Ok(token)) => self.feed(token),
+                Some(Err(err))  => return Some(Err(err)),
+                None            => match self.blocks.pop() {
+                    Some(_) => return Some(Ok(Token::new(TokenType::Dedent, self.position, self.span, String::new()))),
+                    None    => return None,
+                },
+            }
+        }
+
+        self.queue.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::testing::tokens;
+    use super::super::error::LexErrorValue;
+    use super::super::token::TokenType;
+
+    // the blocks the source opens and closes, in order
+    fn blocks(source: &str) -> Vec<TokenType> {
+        tokens(source).unwrap().into_iter()
+            .map(|t| t.token_type)
+            .filter(|t| *t == TokenType::Indent || *t == TokenType::Dedent)
+            .collect()
+    }
+
+    fn error(source: &str) -> String {
+        match tokens(source) {
+            Err(err) => {
+                let LexErrorValue::Constant(message) = err.value;
+                message
+            },
+            Ok(_)    => panic!("laid out {:?}", source),
+        }
+    }
+
+    #[test]
+    fn headers_open_blocks() {
+        use self::TokenType::*;
+
+        assert_eq!(blocks("f := {\n  |n|\n    n\n}"), vec![Indent, Dedent]);
+        assert_eq!(blocks("f := {\n  |n|\n    {\n      |m|\n        m\n    }\n}"), vec![Indent, Indent, Dedent, Dedent]);
+
+        // closed at the end of the source too
+        assert_eq!(blocks("f := {\n  |n|\n    n"), vec![Indent, Dedent]);
+    }
+
+    #[test]
+    fn lines_continue_only_in_brackets() {
+        assert_eq!(blocks("xs := [1,\n  2]\nf := {\n  |n| n\n  |m|\n    g (m,\n       m)\n}"), vec![TokenType::Indent, TokenType::Dedent]);
+
+        assert_eq!(error("x := 1\n  y := 2"), "indented line doesn't follow an arm header");
+        assert_eq!(error("f := {\n  |n|\n    a := n\n      b := a\n}"), "indented line doesn't follow an arm header");
+    }
+
+    #[test]
+    fn rejects_bad_indentation() {
+        assert_eq!(error("f := {\n  |n|\n    n\n   |m| m\n}"), "dedent doesn't match any enclosing block");
+        assert_eq!(error("f := {\n  |n|\n\tn\n}"), "indentation mixes tabs and spaces");
+    }
+}
@@ -1,12 +1,19 @@
-use super::Tokenizer;
+use super::{Tokenizer, LexError, LexResult};
 use super::matcher::*;
 use super::token::{Token, TokenType};
 
 use std::str::Chars;
 use std::rc::Rc;
+use std::mem;
 
-pub fn lexer(data: &mut Chars) -> Lexer {
-    let tokenizer = Tokenizer::new(data);
+pub const KEYWORDS: &[&str] = &[
+    "print",
+    "if",
+];
+
+// `file` tells the spans of tokens from different sources apart
+pub fn lexer(file: usize, data: &mut Chars) -> Lexer {
+    let tokenizer = Tokenizer::new(file, data);
     let mut lexer = Lexer::new(tokenizer);
 
     let eol   = vec!["\n"].iter().map(|&x| x.to_string()).collect();
@@ -25,6 +32,8 @@ pub fn lexer(data: &mut Chars) -> Lexer {
         "!",
         "|",
         "=",
+        "@",
+        "..",
         ".",
     ].iter().map(|&x| x.to_string()).collect();
 
@@ -44,12 +53,9 @@ pub fn lexer(data: &mut Chars) -> Lexer {
         "!=",
     ].iter().map(|&x| x.to_string()).collect();
 
-    let indent = vec![
-        "  ", "\t",
-    ].iter().map(|&x| x.to_string()).collect();
-
     let types = vec![
         "i08",
+        "i16",
         "i32",
         "i64",
         "i128",
@@ -66,9 +72,7 @@ pub fn lexer(data: &mut Chars) -> Lexer {
         "mut",
     ].iter().map(|&x| x.to_string()).collect();
 
-    let keywords = vec![
-        "print"
-    ].iter().map(|&x| x.to_string()).collect();
+    let keywords = KEYWORDS.iter().map(|&x| x.to_string()).collect();
 
     let boolean = vec![
         "true",
@@ -77,27 +81,30 @@ pub fn lexer(data: &mut Chars) -> Lexer {
 
     let matcher_eol            = ConstantMatcher::new(TokenType::EOL, eol);
     let matcher_space          = ConstantMatcher::new(TokenType::Whitespace, space);
-    let matcher_indent         = ConstantMatcher::new(TokenType::Indent, indent);
+    let matcher_indent         = IndentMatcher {};
     let matcher_keywords       = KeyMatcher::new(TokenType::Keyword, keywords);
     let matcher_operator       = ConstantMatcher::new(TokenType::Operator, operators);
     let matcher_symbol         = ConstantMatcher::new(TokenType::Symbol, symbols);
     let matcher_boolean        = KeyMatcher::new(TokenType::BoolLiteral, boolean);
     let matcher_types          = KeyMatcher::new(TokenType::Type, types);
     let matcher_whitespace     = WhitespaceMatcher {};
+    let matcher_comment        = CommentMatcher {};
     let matcher_int_literal    = IntLiteralMatcher {};
     let matcher_float_literal  = FloatLiteralMatcher {};
     let matcher_identifier     = IdentifierMatcher {};
     let matcher_string_literal = StringLiteralMatcher {};
 
     lexer.matchers_mut().push(Rc::new(matcher_eol));
-    lexer.matchers_mut().push(Rc::new(matcher_space));
     lexer.matchers_mut().push(Rc::new(matcher_indent));
+    lexer.matchers_mut().push(Rc::new(matcher_space));
     lexer.matchers_mut().push(Rc::new(matcher_whitespace));
+    lexer.matchers_mut().push(Rc::new(matcher_comment));
     lexer.matchers_mut().push(Rc::new(matcher_keywords));
-    lexer.matchers_mut().push(Rc::new(matcher_operator));
-    lexer.matchers_mut().push(Rc::new(matcher_symbol));
+    // numbers go before operators, which would take a number's sign
     lexer.matchers_mut().push(Rc::new(matcher_float_literal));
     lexer.matchers_mut().push(Rc::new(matcher_int_literal));
+    lexer.matchers_mut().push(Rc::new(matcher_operator));
+    lexer.matchers_mut().push(Rc::new(matcher_symbol));
     lexer.matchers_mut().push(Rc::new(matcher_string_literal));
     lexer.matchers_mut().push(Rc::new(matcher_boolean));
     lexer.matchers_mut().push(Rc::new(matcher_types));
@@ -108,6 +115,8 @@ pub fn lexer(data: &mut Chars) -> Lexer {
 pub struct Lexer {
     tokenizer: Tokenizer,
     matchers: Vec<Rc<Matcher>>,
+    // comments waiting for the token they come before
+    trivia: Vec<Token>,
 }
 
 #[allow(dead_code)]
@@ -116,17 +125,23 @@ impl Lexer {
         Lexer {
             tokenizer,
             matchers: Vec::new(),
+            trivia:   Vec::new(),
         }
     }
 
-    pub fn match_token(&mut self) -> Option<Token> {
+    // a character no matcher takes is an error of its own, skipped over
+    pub fn match_token(&mut self) -> LexResult<Token> {
         for matcher in &mut self.matchers {
             match self.tokenizer.try_match_token(matcher.as_ref()) {
-                Some(t) => return Some(t),
+                Some(t) => return t,
                 None => continue,
             }
         }
-        None
+
+        let position = self.tokenizer.pos;
+        let c        = self.tokenizer.next().unwrap();
+
+        Err(LexError::new(&format!("unexpected character {:?}", c), position))
     }
 
     pub fn matchers(&self) -> &Vec<Rc<Matcher>> {
@@ -136,15 +151,20 @@ impl Lexer {
     pub fn matchers_mut(&mut self) -> &mut Vec<Rc<Matcher>> {
         &mut self.matchers
     }
+
+    // comments after the last token, which have nothing to be attached to
+    pub fn trailing_trivia(&self) -> &[Token] {
+        &self.trivia
+    }
 }
 
 impl Iterator for Lexer {
-    type Item = Token;
+    type Item = LexResult<Token>;
 
-    fn next(&mut self) -> Option<Token> {
-        let token = match self.match_token() {
-            Some(n) => n,
-            None    => return None,
+    fn next(&mut self) -> Option<LexResult<Token>> {
+        let mut token = match self.match_token() {
+            Ok(t)    => t,
+            Err(err) => return Some(Err(err)),
         };
         match token.token_type {
             TokenType::EOF => None,
@@ -154,7 +174,64 @@ impl Iterator for Lexer {
                     None => None,
                 }
             }
-            _ => Some(token),
+            TokenType::Comment => {
+                self.trivia.push(token);
+                self.next()
+            }
+            _ => {
+                token.set_trivia(mem::take(&mut self.trivia));
+                Some(Ok(token))
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::lexer;
+    use super::super::error::LexErrorValue;
+    use super::super::token::{TokenType, TokenPosition};
+
+    // the tokens and errors in the order the source gives them
+    fn lexed(source: &str) -> Vec<Result<String, (String, TokenPosition)>> {
+        lexer(0, &mut source.chars()).map(|t| match t {
+            Ok(token) => Ok(token.content().clone()),
+            Err(err)  => {
+                let LexErrorValue::Constant(message) = err.value;
+                Err((message, err.position))
+            },
+        }).collect()
+    }
+
+    fn at(line: usize, col: usize) -> TokenPosition {
+        TokenPosition { line, col }
+    }
+
+    #[test]
+    fn errors_dont_stop_lexing() {
+        assert_eq!(lexed("a $ b"), vec![
+            Ok("a".to_string()), Err(("unexpected character '$'".to_string(), at(1, 2))), Ok("b".to_string()),
+        ]);
+
+        assert_eq!(lexed("x\n'ab' y")[2], Err(("char literal holds 2 characters, expected one".to_string(), at(2, 0))));
+        assert_eq!(lexed("x\n'ab' y")[3], Ok("y".to_string()));
+    }
+
+    #[test]
+    fn unterminated_literals() {
+        assert_eq!(lexed("\"abc"), vec![Err(("unterminated string literal".to_string(), at(1, 0)))]);
+        assert_eq!(lexed("#[ a #[ b ]#"), vec![Err(("unterminated block comment".to_string(), at(1, 0)))]);
+        assert_eq!(lexed("\"\\q\"")[0], Err(("invalid character escape: \\q".to_string(), at(1, 1))));
+    }
+
+    #[test]
+    fn comments_ride_on_the_next_token() {
+        let tokens = lexer(0, &mut "# one\nx #[ two #[ nested ]# ]# y # three".chars()).map(|t| t.unwrap()).collect::<Vec<_>>();
+        let trivia = |i: usize| tokens[i].trivia().iter().map(|t| t.content().clone()).collect::<Vec<_>>();
+
+        assert_eq!(tokens.iter().map(|t| t.token_type.clone()).collect::<Vec<_>>(), vec![TokenType::EOL, TokenType::Identifier, TokenType::Identifier]);
+        assert_eq!(trivia(0), vec!["# one"]);
+        assert_eq!(trivia(1), Vec::<String>::new());
+        assert_eq!(trivia(2), vec!["#[ two #[ nested ]# ]#"]);
+    }
+}
@@ -1,6 +1,7 @@
 
-use super::Tokenizer;
-use super::token::{Token, TokenType};
+use super::{Tokenizer, LexError, LexResult};
+use super::token::{Token, TokenType, TokenPosition};
+use super::lexer::KEYWORDS;
 
 macro_rules! token {
     ($tokenizer:expr, $token_type:ident, $accum:expr) => {{
@@ -9,20 +10,186 @@ macro_rules! token {
     ($tokenizer:expr, $token_type:expr, $accum:expr) => {{
         let tokenizer  = $tokenizer  as &$crate::sloth::syntax::lexer::Tokenizer;
         let token_type = $token_type as $crate::sloth::syntax::lexer::token::TokenType;
-        Some(Token::new(token_type, tokenizer.last_position(), $accum))
+        Some(Ok(Token::new(token_type, tokenizer.last_position(), tokenizer.last_span(), $accum)))
     }};
 }
 
+// a matcher that recognises its token but finds it malformed consumes it and gives an error
 pub trait Matcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token>;
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>>;
 }
 
+fn lex_error(message: &str, position: TokenPosition) -> Option<LexResult<Token>> {
+    Some(Err(LexError::new(message, position)))
+}
+
+// an int literal, or `None` when its digits don't fit
+fn int_literal(digits: &str, radix: u32, negative: bool) -> Option<i128> {
+    let sign = if negative { "-" } else { "" };
+
+    i128::from_str_radix(&format!("{}{}", sign, digits), radix).ok()
+}
+
+// the widths a number literal can be written with, `255u08` or `1.5f32`
+const SUFFIXES: &[&str] = &["i08", "i16", "i32", "i64", "i128", "u08", "u16", "u32", "u64", "u128", "f32", "f64"];
+
+// the digits ahead in `radix`, leaving out the `_` that can separate them. a `_` has to sit
+// between two digits, `1_` and `1__0` are errors
+fn digits(tokenizer: &mut Tokenizer, radix: u32, accum: &mut String) -> LexResult<()> {
+    let mut after_digit = accum.chars().last().map_or(false, |c| c.is_digit(radix));
+
+    while let Some(&c) = tokenizer.peek() {
+        if c.is_digit(radix) {
+            accum.push(c);
+            after_digit = true
+        } else if c == '_' {
+            let before_digit = tokenizer.peek_n(1).map_or(false, |c| c.is_digit(radix));
+
+            if !after_digit || !before_digit {
+                return Err(LexError::new("`_` has to be between digits", tokenizer.pos))
+            }
+
+            after_digit = false
+        } else {
+            break
+        }
+
+        tokenizer.next();
+    }
+
+    Ok(())
+}
+
+// whether a `-` or `+` ahead is a number's sign, which it is unless it comes after something
+// an operation could have on its left: `x -1` is `x - 1`, `f(-1)`, `y := -1` and `print -1`
+// are signed
+fn sign_position(tokenizer: &Tokenizer) -> bool {
+    let mut n = 0;
+
+    while tokenizer.peek_back(n) == Some(&' ') || tokenizer.peek_back(n) == Some(&'\t') {
+        n += 1
+    }
+
+    let mut word = String::new();
+
+    while let Some(&c) = tokenizer.peek_back(n) {
+        if !(c.is_alphanumeric() || c == '_') {
+            break
+        }
+
+        word.insert(0, c);
+        n += 1
+    }
+
+    if !word.is_empty() {
+        return KEYWORDS.contains(&word.as_str())
+    }
+
+    match tokenizer.peek_back(n) {
+        Some(&c) => !(c == ')' || c == ']' || c == '}' || c == '"' || c == '\''),
+        None     => true,
+    }
+}
+
+// the sign ahead of a number, consumed, `Some(false)` for `-`
+fn sign(tokenizer: &mut Tokenizer) -> Option<bool> {
+    let sign = match tokenizer.peek() {
+        Some(&'-') => Some(false),
+        Some(&'+') => Some(true),
+        _          => None,
+    };
+
+    if sign.is_some() && sign_position(tokenizer) {
+        tokenizer.advance(1);
+        sign
+    } else {
+        None
+    }
+}
+
+// the width suffix right after a literal's digits, if it has one
+fn suffix(tokenizer: &mut Tokenizer) -> Option<&'static str> {
+    for suffix in SUFFIXES {
+        let len     = suffix.chars().count();
+        let matches = suffix.chars().enumerate().all(|(i, c)| tokenizer.peek_n(i) == Some(&c));
+
+        let follows = match tokenizer.peek_n(len) {
+            Some(&c) => c.is_alphanumeric() || c == '_',
+            None     => false,
+        };
+
+        if matches && !follows {
+            tokenizer.advance(len);
+            return Some(suffix)
+        }
+    }
+
+    None
+}
+
+// whether an int is in range of the width its suffix gives it
+fn int_fits(n: i128, suffix: &str) -> bool {
+    match suffix {
+        "i08"  => n >= i8::min_value() as i128 && n <= i8::max_value() as i128,
+        "i16"  => n >= i16::min_value() as i128 && n <= i16::max_value() as i128,
+        "i32"  => n >= i32::min_value() as i128 && n <= i32::max_value() as i128,
+        "i64"  => n >= i64::min_value() as i128 && n <= i64::max_value() as i128,
+        "u08"  => n >= 0 && n <= u8::max_value() as i128,
+        "u16"  => n >= 0 && n <= u16::max_value() as i128,
+        "u32"  => n >= 0 && n <= u32::max_value() as i128,
+        "u64"  => n >= 0 && n <= u64::max_value() as i128,
+        "u128" => n >= 0,
+        _      => true,
+    }
+}
+
+// an int literal's token, in decimal. `u128` is the one width with ints past `i128`'s range
+fn int_token(tokenizer: &Tokenizer, digits: &str, radix: u32, negative: bool, suffix: Option<&str>) -> Option<LexResult<Token>> {
+    let text = tokenizer.last_content();
+
+    match (int_literal(digits, radix, negative), suffix) {
+        (Some(n), _) => number_token(tokenizer, n.to_string(), false, suffix),
+
+        // past `i128` an int can only be a `u128`, the checker makes one without a suffix that
+        (None, Some("u128")) | (None, None) if !negative => match u128::from_str_radix(digits, radix) {
+            Ok(n)  => token!(tokenizer, IntLiteral, format!("{}{}", n, suffix.unwrap_or(""))),
+            Err(_) => lex_error(&format!("int literal {} doesn't fit in {}", text, suffix.unwrap_or("128 bits")), tokenizer.last_position()),
+        },
+
+        (None, Some(s)) => lex_error(&format!("int literal {} doesn't fit in {}", text, s), tokenizer.last_position()),
+        (None, None)    => lex_error(&format!("int literal {} doesn't fit in 128 bits", text), tokenizer.last_position()),
+    }
+}
+
+// a literal's token keeps its suffix after the number, for the parser to make a conversion of
+fn number_token(tokenizer: &Tokenizer, literal: String, float: bool, suffix: Option<&str>) -> Option<LexResult<Token>> {
+    let text = tokenizer.last_content();
+
+    match suffix {
+        Some(s) if s.starts_with('f') => match literal.parse::<f64>() {
+            Ok(n) if s == "f32" && n.abs() > f32::MAX as f64 => lex_error(&format!("float literal {} doesn't fit in {}", text, s), tokenizer.last_position()),
+            _                                                => token!(tokenizer, FloatLiteral, format!("{}{}", literal, s)),
+        },
+
+        Some(_) if float => lex_error(&format!("float literal {} can't have an int suffix", text), tokenizer.last_position()),
+
+        Some(s) => match literal.parse::<i128>() {
+            Ok(n) if int_fits(n, s) => token!(tokenizer, IntLiteral, format!("{}{}", literal, s)),
+            _                       => lex_error(&format!("int literal {} doesn't fit in {}", text, s), tokenizer.last_position()),
+        },
+
+        None if float => token!(tokenizer, FloatLiteral, literal),
+        None          => token!(tokenizer, IntLiteral, literal),
+    }
+}
+
+// whitespace within a line, line breaks are tokens of their own
 pub struct WhitespaceMatcher;
 
 impl Matcher for WhitespaceMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
         let mut found = false;
-        while !tokenizer.end() && tokenizer.peek().unwrap().is_whitespace() {
+        while !tokenizer.end() && tokenizer.peek().unwrap().is_whitespace() && tokenizer.peek() != Some(&'\n') {
             found = true;
             tokenizer.next();
         }
@@ -34,107 +201,214 @@ impl Matcher for WhitespaceMatcher {
     }
 }
 
+// the spaces and tabs a line starts with, which `Layout` turns into blocks
+pub struct IndentMatcher;
+
+impl Matcher for IndentMatcher {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
+        if tokenizer.pos.col != 0 {
+            return None
+        }
+
+        let mut accum = String::new();
+
+        while let Some(&c) = tokenizer.peek() {
+            if c != ' ' && c != '\t' {
+                break
+            }
+
+            accum.push(tokenizer.next().unwrap())
+        }
+
+        if accum.is_empty() {
+            None
+        } else {
+            token!(tokenizer, Indent, accum)
+        }
+    }
+}
+
+// `#` to the end of the line, or `#[` to its `]#`, block comments nest
+pub struct CommentMatcher;
+
+impl Matcher for CommentMatcher {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
+        if tokenizer.peek() != Some(&'#') {
+            return None
+        }
+
+        let mut accum = String::new();
+
+        if tokenizer.peek_n(1) != Some(&'[') {
+            while !tokenizer.end() && tokenizer.peek() != Some(&'\n') {
+                accum.push(tokenizer.next().unwrap())
+            }
+
+            return token!(tokenizer, Comment, accum)
+        }
+
+        let mut depth = 0;
+
+        while !tokenizer.end() {
+            match (*tokenizer.peek().unwrap(), tokenizer.peek_n(1)) {
+                ('#', Some(&'[')) => depth += 1,
+                (']', Some(&'#')) => depth -= 1,
+                _                 => {
+                    accum.push(tokenizer.next().unwrap());
+                    continue
+                },
+            }
+
+            accum.push(tokenizer.next().unwrap());
+            accum.push(tokenizer.next().unwrap());
+
+            if depth == 0 {
+                return token!(tokenizer, Comment, accum)
+            }
+        }
+
+        lex_error("unterminated block comment", tokenizer.last_position())
+    }
+}
+
+// ints in decimal, or in hex, octal or binary behind `0x`, `0o` or `0b`
 pub struct IntLiteralMatcher {}
 
 impl Matcher for IntLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
         let mut accum = String::new();
-        let prefix = match tokenizer.peek() {
-            Some(&'-') => Some(false),
-            Some(&'+') => Some(true),
-            _          => None,
-        };
-        if let Some(_) = prefix {
-            tokenizer.advance(1)
+        let prefix    = sign(tokenizer);
+
+        let radix = match (tokenizer.peek(), tokenizer.peek_n(1)) {
+            (Some(&'0'), Some(&'x')) => 16,
+            (Some(&'0'), Some(&'o')) => 8,
+            (Some(&'0'), Some(&'b')) => 2,
+            _                        => 10,
         };
-        while !tokenizer.end() && tokenizer.peek().unwrap().is_digit(10) {
-            accum.push(tokenizer.next().unwrap());
+
+        if radix != 10 {
+            tokenizer.advance(2)
+        } else if !tokenizer.peek()?.is_digit(10) {
+            return None
         }
-        if !accum.is_empty() {
-            let literal: String = if Some(false) == prefix {
-                match i64::from_str_radix(accum.as_str(), 10) {
-                    Ok(result) => format!("-{}", result),
-                    Err(error) => panic!("unable to parse int: {}", error)
-                }
-            } else {
-                match u64::from_str_radix(accum.as_str(), 10) {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse int: {}", error)
-                }
-            };
-            token!(tokenizer, IntLiteral, literal)
-        } else {
-            None
+
+        if let Err(err) = digits(tokenizer, radix, &mut accum) {
+            return Some(Err(err))
+        }
+
+        if let Some(&c) = tokenizer.peek() {
+            if c.is_digit(10) {
+                return lex_error(&format!("{} isn't a digit in base {}", c, radix), tokenizer.pos)
+            }
+        }
+
+        if accum.is_empty() {
+            return lex_error(&format!("no digits in {}", tokenizer.last_content()), tokenizer.last_position())
+        }
+
+        let text   = tokenizer.last_content();
+        let suffix = suffix(tokenizer);
+
+        match suffix {
+            Some(s) if s.starts_with('f') && radix != 10 => lex_error(&format!("{} can't be a float", text), tokenizer.last_position()),
+            _                                            => int_token(tokenizer, &accum, radix, Some(false) == prefix, suffix),
         }
     }
 }
 
+// decimal numbers with a point or an exponent, `1.5` or `6.02e23`, or without either, which
+// are ints. the int matcher is left the ones behind a radix prefix
 pub struct FloatLiteralMatcher;
 
 impl Matcher for FloatLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
         let mut accum = String::new();
+        let prefix    = sign(tokenizer);
 
-        let prefix = match tokenizer.peek() {
-            Some(&'-') => Some(false),
-            Some(&'+') => Some(true),
-            _          => None,
-        };
-        if let Some(_) = prefix {
-            tokenizer.advance(1)
-        };
+        match (tokenizer.peek(), tokenizer.peek_n(1)) {
+            (Some(&'0'), Some(&'x')) | (Some(&'0'), Some(&'o')) | (Some(&'0'), Some(&'b')) => return None,
+            _                                                                            => (),
+        }
 
-        let curr = tokenizer.next().unwrap();
+        // `.` on its own, or starting `..`, is a symbol
+        let curr = tokenizer.next()?;
         if curr.is_digit(10) {
             accum.push(curr)
-        } else if curr == '.' {
+        } else if curr == '.' && tokenizer.peek().map_or(false, |c| c.is_digit(10)) {
             accum.push_str("0.")
         } else {
             return None
         }
+
+        // a second decimal point is reported where it is, the rest of the literal goes with it
+        let mut extra_point = None;
+
         while !tokenizer.end() {
             let current = *tokenizer.peek().unwrap();
-            if !current.is_whitespace() && current.is_digit(10) || current == '.' {
-                if current == '.' && accum.contains('.') {
-                    panic!("illegal decimal point")
+
+            // `1..9` is a range, not a malformed float
+            if current == '.' && tokenizer.peek_n(1) == Some(&'.') {
+                break
+            }
+
+            if current == '.' {
+                if accum.contains('.') && extra_point.is_none() {
+                    extra_point = Some(tokenizer.pos)
                 }
                 accum.push(tokenizer.next().unwrap())
+            } else if current.is_digit(10) || current == '_' {
+                if let Err(err) = digits(tokenizer, 10, &mut accum) {
+                    return Some(Err(err))
+                }
             } else {
                 break
             }
         }
 
+        if let Some(position) = extra_point {
+            return lex_error(&format!("second decimal point in {}", accum), position)
+        }
+
         if accum == "0." {
-            None
-        } else if accum.contains('.') {
+            return None
+        }
 
-            let literal: String = if Some(false) == prefix {
-                match accum.parse::<f64>() {
-                    Ok(result) => format!("-{}", result),
-                    Err(error) => panic!("unable to parse float: {}", error)
-                }
-            } else {
-                match accum.parse::<f64>() {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse float: {}", error)
-                }
-            };
+        // an exponent needs digits, `2e` is a number and a name
+        let sign = match tokenizer.peek_n(1) {
+            Some(&'-') | Some(&'+') => 1,
+            _                       => 0,
+        };
 
-            token!(tokenizer, FloatLiteral, literal)
-        } else {
-            let literal: String = if Some(false) == prefix {
-                match i64::from_str_radix(accum.as_str(), 10) {
-                    Ok(result) => format!("-{}", result),
-                    Err(error) => panic!("unable to parse int: {}", error)
-                }
-            } else {
-                match u64::from_str_radix(accum.as_str(), 10) {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse int: {}", error)
-                }
+        let exponent = (tokenizer.peek() == Some(&'e') || tokenizer.peek() == Some(&'E'))
+                    && tokenizer.peek_n(1 + sign).map_or(false, |c| c.is_digit(10));
+
+        if exponent {
+            accum.push('e');
+            tokenizer.advance(1);
+
+            if sign == 1 {
+                accum.push(tokenizer.next().unwrap())
+            }
+
+            if let Err(err) = digits(tokenizer, 10, &mut accum) {
+                return Some(Err(err))
+            }
+        }
+
+        let text   = tokenizer.last_content();
+        let suffix = suffix(tokenizer);
+
+        if accum.contains('.') || exponent {
+            let literal: String = match accum.parse::<f64>() {
+                Ok(result) if result.is_infinite()  => return lex_error(&format!("float literal {} doesn't fit in 64 bits", text), tokenizer.last_position()),
+                Ok(result) if Some(false) == prefix => format!("-{}", result),
+                Ok(result)                          => result.to_string(),
+                Err(_)                              => return lex_error(&format!("malformed float literal {}", accum), tokenizer.last_position()),
             };
 
-            token!(tokenizer, IntLiteral, literal)
+            number_token(tokenizer, literal, true, suffix)
+        } else {
+            int_token(tokenizer, &accum, 10, Some(false) == prefix, suffix)
         }
     }
 }
@@ -142,25 +416,32 @@ impl Matcher for FloatLiteralMatcher {
 pub struct StringLiteralMatcher {}
 
 impl Matcher for StringLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
         let mut raw_marker = false;
-        let delimeter  = match *tokenizer.peek().unwrap() {
-            '"'  => Some('"'),
-            '\'' => Some('\''),
+        let delimeter  = match *tokenizer.peek()? {
+            '"'  => '"',
+            '\'' => '\'',
             'r' if tokenizer.peek_n(1) == Some(&'"') => {
                 raw_marker = true;
                 tokenizer.advance(1);
 
-                Some('"')
+                '"'
             },
             _ => return None,
         };
         tokenizer.advance(1);
         let mut string       = String::new();
         let mut found_escape = false;
+        let mut terminated   = false;
+
+        // a bad escape doesn't stop the literal, it's reported once the whole of it is read
+        let mut bad_escape: Option<(char, TokenPosition)> = None;
+        let mut escape_at = tokenizer.pos;
+
         while !tokenizer.end() {
             if raw_marker {
                 if tokenizer.peek().unwrap() == &'"' {
+                    terminated = true;
                     break
                 }
                 string.push(tokenizer.next().unwrap())
@@ -171,31 +452,51 @@ impl Matcher for StringLiteralMatcher {
                         'n' => '\n',
                         'r' => '\r',
                         't' => '\t',
-                        s => panic!("invalid character escape: {}", s),
+                        c   => {
+                            if bad_escape.is_none() {
+                                bad_escape = Some((c, escape_at))
+                            }
+                            c
+                        },
                     }
                 );
                 found_escape = false
             } else {
                 match *tokenizer.peek().unwrap() {
                     '\\' => {
+                        escape_at = tokenizer.pos;
                         tokenizer.next();
                         found_escape = true
                     },
-                    c if c == delimeter.unwrap() => break,
+                    c if c == delimeter => {
+                        terminated = true;
+                        break
+                    },
                     _ => string.push(tokenizer.next().unwrap()),
                 }
             }
         }
+
+        if !terminated {
+            let what = if delimeter == '"' { "string" } else { "char" };
+            return lex_error(&format!("unterminated {} literal", what), tokenizer.last_position())
+        }
+
         tokenizer.advance(1);
-        match delimeter.unwrap() {
+
+        if let Some((c, position)) = bad_escape {
+            return lex_error(&format!("invalid character escape: \\{}", c), position)
+        }
+
+        match delimeter {
             '"'  => {
                 token!(tokenizer, StringLiteral, string)
             },
             _ => {
-                if string.len() == 1 {
-                    token!(tokenizer, CharLiteral, string)
-                } else {
-                    panic!("invalid char literal")
+                match string.chars().count() {
+                    1 => token!(tokenizer, CharLiteral, string),
+                    0 => lex_error("empty char literal", tokenizer.last_position()),
+                    n => lex_error(&format!("char literal holds {} characters, expected one", n), tokenizer.last_position()),
                 }
             },
         }
@@ -217,7 +518,7 @@ impl ConstantMatcher {
 }
 
 impl Matcher for ConstantMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
         for constant in self.constants.clone() {
             let dat = tokenizer.clone().take(constant.len());
             if dat.size_hint().1.unwrap() != constant.len() {
@@ -247,7 +548,7 @@ impl KeyMatcher {
 }
 
 impl Matcher for KeyMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
         for constant in self.constants.clone() {
             let dat = tokenizer.clone().take(constant.len());
             if dat.size_hint().1.unwrap() != constant.len() {
@@ -271,7 +572,7 @@ impl Matcher for KeyMatcher {
 pub struct IdentifierMatcher;
 
 impl Matcher for IdentifierMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<LexResult<Token>> {
         let mut identifier = String::new();
         while !tokenizer.end() {
             let current = *tokenizer.peek().unwrap();
@@ -288,3 +589,98 @@ impl Matcher for IdentifierMatcher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::lexer;
+    use super::super::error::LexErrorValue;
+    use super::super::token::TokenType;
+
+    fn lexed(source: &str) -> Vec<(TokenType, String)> {
+        lexer(0, &mut source.chars()).map(|t| {
+            let t = t.unwrap();
+            (t.token_type.clone(), t.content().clone())
+        }).collect()
+    }
+
+    fn int(n: &str) -> (TokenType, String) {
+        (TokenType::IntLiteral, n.to_string())
+    }
+
+    fn other(token_type: TokenType, content: &str) -> (TokenType, String) {
+        (token_type, content.to_string())
+    }
+
+    // the message of the first error lexing the source runs into
+    fn error(source: &str) -> String {
+        match lexer(0, &mut source.chars()).find(|t| t.is_err()) {
+            Some(Err(err)) => {
+                let LexErrorValue::Constant(message) = err.value;
+                message
+            },
+            _              => panic!("lexed {:?}", lexed(source)),
+        }
+    }
+
+    #[test]
+    fn sign_after_keywords() {
+        assert_eq!(lexed("print -1"), vec![other(TokenType::Keyword, "print"), int("-1")]);
+        assert_eq!(lexed("print -0"), vec![other(TokenType::Keyword, "print"), int("0")]);
+        assert_eq!(lexed("if -1"), vec![other(TokenType::Keyword, "if"), int("-1")]);
+    }
+
+    #[test]
+    fn sign_after_operators_and_openings() {
+        assert_eq!(lexed("(-1")[1], int("-1"));
+        assert_eq!(lexed("[-1")[1], int("-1"));
+        assert_eq!(lexed("x := -128i08")[3], int("-128i08"));
+        assert_eq!(lexed("x * -3")[2], int("-3"));
+        assert_eq!(lexed("|-5..-2|"), vec![
+            other(TokenType::Symbol, "|"), int("-5"), other(TokenType::Symbol, ".."), int("-2"), other(TokenType::Symbol, "|"),
+        ]);
+    }
+
+    #[test]
+    fn minus_after_operands() {
+        let minus = other(TokenType::Operator, "-");
+
+        assert_eq!(lexed("x -1"), vec![other(TokenType::Identifier, "x"), minus.clone(), int("1")]);
+        assert_eq!(lexed("printer -1")[1], minus);
+        assert_eq!(lexed("5-3"), vec![int("5"), minus.clone(), int("3")]);
+        assert_eq!(lexed("(x) -1")[3], minus.clone());
+        assert_eq!(lexed("xs[0] -1")[4], minus);
+    }
+
+    #[test]
+    fn u128_past_i128() {
+        let max = "340282366920938463463374607431768211455";
+
+        assert_eq!(lexed(&format!("{}u128", max)), vec![int(&format!("{}u128", max))]);
+        assert_eq!(lexed(max), vec![int(max)]);
+        assert_eq!(lexed("0xffffffffffffffffffffffffffffffff"), vec![int(max)]);
+
+        assert_eq!(error("340282366920938463463374607431768211456"), "int literal 340282366920938463463374607431768211456 doesn't fit in 128 bits");
+        assert_eq!(error("340282366920938463463374607431768211456u128"), "int literal 340282366920938463463374607431768211456u128 doesn't fit in u128");
+        assert_eq!(error("170141183460469231731687303715884105728i128"), "int literal 170141183460469231731687303715884105728i128 doesn't fit in i128");
+        assert_eq!(error("-1u128"), "int literal -1u128 doesn't fit in u128");
+    }
+
+    #[test]
+    fn suffix_ranges_take_the_sign() {
+        assert_eq!(lexed("-128i08"), vec![int("-128i08")]);
+        assert_eq!(lexed("-0x80i08"), vec![int("-128i08")]);
+        assert_eq!(error("-129i08"), "int literal -129i08 doesn't fit in i08");
+        assert_eq!(error("128i08"), "int literal 128i08 doesn't fit in i08");
+        assert_eq!(error("256u08"), "int literal 256u08 doesn't fit in u08");
+    }
+
+    #[test]
+    fn separators_sit_between_digits() {
+        assert_eq!(lexed("1_000_000"), vec![int("1000000")]);
+        assert_eq!(lexed("0xFF_FF"), vec![int("65535")]);
+
+        for source in &["1_", "1__0", "1_.5", "1._5", "1_e5", "0x_ff"] {
+            assert_eq!(error(source), "`_` has to be between digits", "{}", source);
+        }
+    }
+}
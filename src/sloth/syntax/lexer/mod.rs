@@ -2,8 +2,14 @@ pub mod token;
 pub mod tokenizer;
 pub mod matcher;
 pub mod lexer;
+pub mod error;
+pub mod layout;
 
 pub use self::token::*;
 pub use self::tokenizer::*;
 pub use self::matcher::*;
 pub use self::lexer::*;
+pub use self::error::*;
+pub use self::layout::*;
+
+pub type LexResult<T> = Result<T, LexError>;
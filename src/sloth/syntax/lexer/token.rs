@@ -13,7 +13,9 @@ pub enum TokenType {
     Type,
     Keyword,
     Whitespace,
+    Comment,
     Indent,
+    Dedent,
     EOL,
     EOF,
 }
@@ -47,26 +49,64 @@ impl TokenPosition {
     }
 }
 
+// the bytes `start .. end` of a source file, files are told apart by the id they're lexed with
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Span {
+    pub file:  usize,
+    pub start: usize,
+    pub end:   usize,
+}
+
+impl Span {
+    pub fn new(file: usize, start: usize, end: usize) -> Span {
+        Span {
+            file, start, end,
+        }
+    }
+
+    // from wherever the first of the two starts to wherever the last ends
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.file, self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub position:   TokenPosition,
+    pub span:       Span,
     content:        String,
+    // the comments written before the token, in order
+    trivia:         Vec<Token>,
 }
 
 #[allow(dead_code)]
 impl Token {
-    pub fn new(token_type: TokenType, position: TokenPosition, content: String) -> Token {
+    pub fn new(token_type: TokenType, position: TokenPosition, span: Span, content: String) -> Token {
         Token {
             token_type,
             position,
+            span,
             content,
+            trivia: Vec::new(),
         }
     }
 
     pub fn content(&self) -> &String {
         &self.content
     }
+
+    pub fn trivia(&self) -> &[Token] {
+        &self.trivia
+    }
+
+    pub fn set_trivia(&mut self, trivia: Vec<Token>) {
+        self.trivia = trivia
+    }
 }
 
 impl PartialEq for Token {
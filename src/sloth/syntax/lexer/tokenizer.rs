@@ -4,12 +4,14 @@ use super::*;
 pub struct Snapshot {
     pub pos: TokenPosition,
     pub index: usize,
+    pub byte:  usize,
 }
 
 impl Snapshot {
-    pub fn new(index: usize, pos: TokenPosition) -> Snapshot {
+    pub fn new(index: usize, byte: usize, pos: TokenPosition) -> Snapshot {
         Snapshot {
             index,
+            byte,
             pos,
         }
     }
@@ -18,7 +20,10 @@ impl Snapshot {
 #[derive(Clone, Debug)]
 pub struct Tokenizer {
     pub pos:   TokenPosition,
+    pub file:  usize,
     index:     usize,
+    // where `index` is in the source's utf-8, for spans
+    byte:      usize,
     items:     Vec<char>,
     snapshots: Vec<Snapshot>,
 }
@@ -33,10 +38,12 @@ impl Iterator for Tokenizer {
 
 #[allow(dead_code)]
 impl Tokenizer {
-    pub fn new(items: &mut Iterator<Item = char>) -> Tokenizer {
+    pub fn new(file: usize, items: &mut Iterator<Item = char>) -> Tokenizer {
         Tokenizer {
             index:     0,
+            byte:      0,
             pos:       TokenPosition::default(),
+            file,
             items:     items.collect(),
             snapshots: Vec::new(),
         }
@@ -61,6 +68,14 @@ impl Tokenizer {
         Some(&self.items[self.index + n])
     }
 
+    // the `n`th character before the tokenizer, counting back from the one right behind it
+    pub fn peek_back(&self, n: usize) -> Option<&char> {
+        if n >= self.index {
+            return None
+        }
+        Some(&self.items[self.index - 1 - n])
+    }
+
     pub fn read(&mut self) -> Option<&char> {
         if self.end() {
             return None
@@ -79,13 +94,15 @@ impl Tokenizer {
                     }
                     _ => self.pos.col += 1
                 }
+
+                self.byte += item.len_utf8()
             }
             self.index += a
         }
     }
 
     pub fn take_snapshot(&mut self) {
-        self.snapshots.push(Snapshot::new(self.index, self.pos));
+        self.snapshots.push(Snapshot::new(self.index, self.byte, self.pos));
     }
 
     pub fn peek_snapshot(&self) -> Option<&Snapshot> {
@@ -95,6 +112,7 @@ impl Tokenizer {
     pub fn rollback_snapshot(&mut self) {
         let snapshot = self.snapshots.pop().unwrap();
         self.index = snapshot.index;
+        self.byte = snapshot.byte;
         self.pos = snapshot.pos;
     }
 
@@ -106,11 +124,28 @@ impl Tokenizer {
         self.peek_snapshot().unwrap().pos
     }
 
-    pub fn try_match_token(&mut self, matcher: &Matcher) -> Option<Token> {
+    // what was consumed since the last snapshot
+    pub fn last_span(&self) -> Span {
+        Span::new(self.file, self.peek_snapshot().unwrap().byte, self.byte)
+    }
+
+    // the source consumed since the last snapshot
+    pub fn last_content(&self) -> String {
+        self.items[self.peek_snapshot().unwrap().index .. self.index].iter().collect()
+    }
+
+    // an empty span where the tokenizer is
+    pub fn span(&self) -> Span {
+        Span::new(self.file, self.byte, self.byte)
+    }
+
+    // an error keeps what the matcher consumed, so lexing carries on after the bad token
+    pub fn try_match_token(&mut self, matcher: &Matcher) -> Option<LexResult<Token>> {
         if self.end() {
-            return Some(Token::new(TokenType::EOF,
-                                   TokenPosition::new(self.index, self.index),
-                                   String::new()));
+            return Some(Ok(Token::new(TokenType::EOF,
+                                      TokenPosition::new(self.index, self.index),
+                                      self.span(),
+                                      String::new())));
         }
 
         self.take_snapshot();
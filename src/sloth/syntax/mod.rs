@@ -3,6 +3,9 @@ pub mod parser;
 pub mod checker;
 pub mod compiler;
 
+#[cfg(test)]
+pub mod testing;
+
 pub use self::lexer::*;
 pub use self::parser::*;
 pub use self::checker::*;
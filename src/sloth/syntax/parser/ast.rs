@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+use std::fmt;
 use std::rc::Rc;
 
 use super::*;
@@ -5,27 +7,103 @@ use super::*;
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Block(Vec<Statement>),
-    Int(i64),
-    Float(f64),
-    Bool(bool),
-    Str(Rc<String>),
-    Char(char),
-    Identifier(Rc<String>, TokenPosition),
+    Int(i128, Span),
+    // an int past `i128`, which only `u128` holds
+    UInt(u128, Span),
+    Float(f64, Span),
+    Bool(bool, Span),
+    Str(Rc<String>, Span),
+    Char(char, Span),
+    Identifier(Rc<String>, TokenPosition, Span),
+    Tuple(Vec<Rc<Expression>>, Span),
+    List(Vec<Rc<Expression>>, Span),
     Operation(Operation),
     Call(Call),
     Index(Index),
+    Convert(Convert),
     Function(Function),
     Arm(Arm),
-    Print(Rc<Expression>),
+    Print(Rc<Expression>, Span),
     EOF,
 }
 
+impl Expression {
+    // the source the expression was parsed from, a block's runs from its first statement to
+    // its last
+    pub fn span(&self) -> Span {
+        match *self {
+            Expression::Block(ref statements) => match (statements.first(), statements.last()) {
+                (Some(first), Some(last)) => first.span().to(last.span()),
+                _                         => Span::default(),
+            },
+
+            Expression::Int(_, span)           |
+            Expression::UInt(_, span)          |
+            Expression::Float(_, span)         |
+            Expression::Bool(_, span)          |
+            Expression::Str(_, span)           |
+            Expression::Char(_, span)          |
+            Expression::Identifier(_, _, span) |
+            Expression::Tuple(_, span)         |
+            Expression::List(_, span)          |
+            Expression::Print(_, span)         => span,
+
+            Expression::Operation(ref operation) => operation.span,
+            Expression::Call(ref call)           => call.span,
+            Expression::Index(ref index)         => index.span,
+            Expression::Convert(ref convert)     => convert.span,
+            Expression::Function(ref function)   => function.span,
+            Expression::Arm(ref arm)             => arm.span,
+
+            Expression::EOF => Span::default(),
+        }
+    }
+
+    // ordering between number literals and between char literals
+    pub fn compare_literal(&self, other: &Expression) -> Option<Ordering> {
+        match (self, other) {
+            (&Expression::Int(a, _), &Expression::Int(b, _))     => Some(a.cmp(&b)),
+            (&Expression::UInt(a, _), &Expression::UInt(b, _))   => Some(a.cmp(&b)),
+            (&Expression::Int(_, _), &Expression::UInt(_, _))    => Some(Ordering::Less),
+            (&Expression::UInt(_, _), &Expression::Int(_, _))    => Some(Ordering::Greater),
+            (&Expression::Float(a, _), &Expression::Float(b, _)) => a.partial_cmp(&b),
+            (&Expression::Int(a, _), &Expression::Float(b, _))   => (a as f64).partial_cmp(&b),
+            (&Expression::Float(a, _), &Expression::Int(b, _))   => a.partial_cmp(&(b as f64)),
+            (&Expression::Char(a, _), &Expression::Char(b, _))   => Some(a.cmp(&b)),
+            _                                                    => None,
+        }
+    }
+
+    // whether two literals have the same value, wherever they're written
+    pub fn same_literal(&self, other: &Expression) -> bool {
+        match (self, other) {
+            (&Expression::Int(a, _), &Expression::Int(b, _))         => a == b,
+            (&Expression::UInt(a, _), &Expression::UInt(b, _))       => a == b,
+            (&Expression::Float(a, _), &Expression::Float(b, _))     => a == b,
+            (&Expression::Bool(a, _), &Expression::Bool(b, _))       => a == b,
+            (&Expression::Char(a, _), &Expression::Char(b, _))       => a == b,
+            (&Expression::Str(ref a, _), &Expression::Str(ref b, _)) => a == b,
+            _                                                        => false,
+        }
+    }
+
+    // whether the literal lies in the inclusive range `lo .. hi`
+    pub fn within(&self, lo: &Expression, hi: &Expression) -> bool {
+        match (lo.compare_literal(self), self.compare_literal(hi)) {
+            (Some(Ordering::Greater), _) | (_, Some(Ordering::Greater)) => false,
+            (Some(_), Some(_))                                          => true,
+            _                                                           => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Operation {
     pub left:     Rc<Expression>,
     pub op:       Operand,
     pub right:    Rc<Expression>,
     pub position: TokenPosition,
+    pub span:     Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -33,6 +111,7 @@ pub struct Call {
     pub callee:   Rc<Expression>,
     pub args:     Vec<Rc<Expression>>,
     pub position: TokenPosition,
+    pub span:     Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -40,19 +119,79 @@ pub struct Index {
     pub id:    Rc<Expression>,
     pub index: Rc<Expression>,
     pub position: TokenPosition,
+    pub span:     Span,
+}
+
+// a number brought to a width, `u08 n`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Convert {
+    pub t:        Type,
+    pub value:    Rc<Expression>,
+    pub position: TokenPosition,
+    pub span:     Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Function {
     pub arms: Vec<Rc<Expression>>,
     pub position: TokenPosition,
+    pub span:     Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Arm {
-    pub params: Vec<Rc<Expression>>,
+    pub params: Vec<Pattern>,
+    pub guard:  Option<Rc<Expression>>,
     pub body:   Rc<Expression>,
     pub position: TokenPosition,
+    pub span:     Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard,
+    Bind(Rc<String>, TokenPosition),
+    Literal(Rc<Expression>),
+    Range(Rc<Expression>, Rc<Expression>),
+    Tuple(Vec<Pattern>),
+    List(Vec<Pattern>, Option<Rc<Pattern>>),
+    As(Rc<String>, Rc<Pattern>, TokenPosition),
+}
+
+impl Pattern {
+    // every name the pattern introduces, left to right
+    pub fn names(&self) -> Vec<(Rc<String>, TokenPosition)> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names(&self, names: &mut Vec<(Rc<String>, TokenPosition)>) {
+        match *self {
+            Pattern::Bind(ref name, position) => names.push((name.clone(), position)),
+
+            Pattern::As(ref name, ref pattern, position) => {
+                names.push((name.clone(), position));
+                pattern.collect_names(names)
+            },
+
+            Pattern::Tuple(ref items) => for item in items {
+                item.collect_names(names)
+            },
+
+            Pattern::List(ref items, ref rest) => {
+                for item in items {
+                    item.collect_names(names)
+                }
+
+                if let Some(ref rest) = *rest {
+                    rest.collect_names(names)
+                }
+            },
+
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range(..) => (),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -62,11 +201,22 @@ pub enum Statement {
     Definition(Definition),
 }
 
+impl Statement {
+    pub fn span(&self) -> Span {
+        match *self {
+            Statement::Expression(ref expression) => expression.span(),
+            Statement::Assignment(ref assignment) => assignment.span,
+            Statement::Definition(ref definition) => definition.span,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Assignment {
     pub left:  Rc<Expression>,
     pub right: Rc<Expression>,
     pub position: TokenPosition,
+    pub span:     Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -75,6 +225,7 @@ pub struct Definition {
     pub name:  Rc<Expression>,
     pub right: Option<Rc<Expression>>,
     pub position: TokenPosition,
+    pub span:     Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -104,13 +255,32 @@ impl Operand {
             _     => None,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Operand::Pow     => "^",
+            Operand::Mul     => "*",
+            Operand::Div     => "/",
+            Operand::Mod     => "%",
+            Operand::Add     => "+",
+            Operand::Sub     => "-",
+            Operand::Equal   => "==",
+            Operand::NEqual  => "!=",
+            Operand::Lt      => "<",
+            Operand::Gt      => ">",
+            Operand::LtEqual => "<=",
+            Operand::GtEqual => ">=",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Mut(Option<Rc<Type>>),
     Signature(Vec<Rc<Type>>),
-    Function(Vec<(Vec<Rc<Type>>, Rc<Type>)>),
+    Function(Vec<Rc<Type>>, Rc<Type>),
+    Tuple(Vec<Rc<Type>>),
+    List(Rc<Type>),
     Identifier(Rc<String>),
     I08,
     I16,
@@ -131,6 +301,9 @@ pub enum Type {
     Float,
     Any,
     Undefined,
+    // an unknown being solved for, and a definition generalised over some of them
+    Var(usize),
+    Forall(Vec<usize>, Rc<Type>),
 }
 
 #[allow(unused)]
@@ -139,29 +312,124 @@ impl Type {
         self == other
     }
 
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match *self {
+            Type::I08 | Type::I16 | Type::I32 | Type::I64 | Type::I128 |
+            Type::U08 | Type::U16 | Type::U32 | Type::U64 | Type::U128 |
+            Type::Int => true,
+            _         => false,
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        match *self {
+            Type::F32 | Type::F64 | Type::Float => true,
+            _                                   => false,
+        }
+    }
+
+    // a built-in type by the name it's written with, which for the sized numbers is also
+    // the suffix of a literal
+    pub fn named(name: &str) -> Option<Type> {
+        match name {
+            "i08"  => Some(Type::I08),
+            "i32"  => Some(Type::I32),
+            "i16"  => Some(Type::I16),
+            "i64"  => Some(Type::I64),
+            "i128" => Some(Type::I128),
+            "f32"  => Some(Type::F32),
+            "f64"  => Some(Type::F64),
+            "u08"  => Some(Type::U08),
+            "u16"  => Some(Type::U16),
+            "u32"  => Some(Type::U32),
+            "u64"  => Some(Type::U64),
+            "u128" => Some(Type::U128),
+            "char" => Some(Type::Char),
+            "str"  => Some(Type::Str),
+            "bool" => Some(Type::Bool),
+            _      => None,
+        }
+    }
+
     pub fn from(v: &Token) -> Option<Type> {
         match v.token_type {
-            TokenType::Type => match v.content().as_str() {
-                "i08"  => Some(Type::I08),
-                "i32"  => Some(Type::I32),
-                "i16"  => Some(Type::I16),
-                "i64"  => Some(Type::I64),
-                "i128" => Some(Type::I128),
-                "f32"  => Some(Type::F32),
-                "f64"  => Some(Type::F64),
-                "u08"  => Some(Type::U08),
-                "u16"  => Some(Type::U16),
-                "u32"  => Some(Type::U32),
-                "u64"  => Some(Type::U64),
-                "u128" => Some(Type::U128),
-                "char" => Some(Type::Char),
-                "str"  => Some(Type::Str),
-                "bool" => Some(Type::Bool),
-                _      => None,
-            },
+            TokenType::Type => Type::named(v.content()),
             
             TokenType::Identifier => Some(Type::Identifier(Rc::new(v.content().clone()))),
             _ => None,
         }
     }
 }
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Mut(None)         => write!(f, "mut"),
+            Type::Mut(Some(ref t))  => write!(f, "mut {}", t),
+            Type::Signature(ref ts) => write!(f, "({})", join(ts, " | ")),
+            Type::Function(ref params, ref ret) => write!(f, "({}) -> {}", join(params, " | "), ret),
+
+            Type::Tuple(ref ts) if ts.len() == 1 => write!(f, "({},)", ts[0]),
+            Type::Tuple(ref ts)                  => write!(f, "({})", join(ts, ", ")),
+
+            Type::List(ref t)       => write!(f, "[{}]", t),
+            Type::Identifier(ref n) => write!(f, "{}", n),
+            Type::I08  => write!(f, "i08"),
+            Type::I16  => write!(f, "i16"),
+            Type::I32  => write!(f, "i32"),
+            Type::I64  => write!(f, "i64"),
+            Type::I128 => write!(f, "i128"),
+            Type::F32  => write!(f, "f32"),
+            Type::F64  => write!(f, "f64"),
+            Type::U08  => write!(f, "u08"),
+            Type::U16  => write!(f, "u16"),
+            Type::U32  => write!(f, "u32"),
+            Type::U64  => write!(f, "u64"),
+            Type::U128 => write!(f, "u128"),
+            Type::Char => write!(f, "char"),
+            Type::Str  => write!(f, "str"),
+            Type::Bool => write!(f, "bool"),
+            Type::Int  => write!(f, "int"),
+            Type::Float     => write!(f, "float"),
+            Type::Any       => write!(f, "any"),
+            Type::Undefined => write!(f, "undefined"),
+            Type::Var(n)    => write!(f, "t{}", n),
+
+            // generalised variables are named a, b, c .. in order
+            Type::Forall(ref vars, ref t) => {
+                let names = (0 .. vars.len()).map(|i| match i {
+                    0 ..= 25 => ((b'a' + i as u8) as char).to_string(),
+                    _        => format!("t{}", i),
+                }).collect::<Vec<String>>();
+
+                write!(f, "forall {}. {}", names.join(" "), t.rename(vars, &names))
+            },
+        }
+    }
+}
+
+impl Type {
+    fn rename(&self, vars: &[usize], names: &[String]) -> Type {
+        let all = |types: &[Rc<Type>]| types.iter().map(|t| Rc::new(t.rename(vars, names))).collect();
+
+        match *self {
+            Type::Var(n) => match vars.iter().position(|&v| v == n) {
+                Some(i) => Type::Identifier(Rc::new(names[i].clone())),
+                None    => Type::Var(n),
+            },
+
+            Type::Function(ref params, ref ret) => Type::Function(all(params), Rc::new(ret.rename(vars, names))),
+            Type::Tuple(ref items)              => Type::Tuple(all(items)),
+            Type::List(ref t)                   => Type::List(Rc::new(t.rename(vars, names))),
+            ref t                               => t.clone(),
+        }
+    }
+}
+
+fn join(types: &[Rc<Type>], separator: &str) -> String {
+    types.iter().map(|t| format!("{}", t)).collect::<Vec<String>>().join(separator)
+}
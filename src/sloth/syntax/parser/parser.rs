@@ -18,16 +18,20 @@ impl Parser {
 
         while self.traveler.remaining() > 1 {
             self.skip_whitespace()?;
-            stack.push(self.statement()?);
+
+            match self.statement()? {
+                Statement::Expression(ref e) if **e == Expression::EOF => (),
+                statement => stack.push(statement),
+            }
         }
 
         Ok(stack)
     }
 
+    // blocks are opened and closed by layout tokens, so those aren't whitespace
     pub fn skip_whitespace(&mut self) -> ParserResult<()> {
         while self.traveler.current_content() == "\n" ||
-              self.traveler.current().token_type == TokenType::EOL ||
-              self.traveler.current().token_type == TokenType::Indent {
+              self.traveler.current().token_type == TokenType::EOL {
 
             self.traveler.next();
 
@@ -102,16 +106,17 @@ impl Parser {
         }
     }
 
-    fn try_call(&mut self, callee: Expression) -> ParserResult<Expression> {
+    // `position` is where the callee starts
+    fn try_call(&mut self, callee: Expression, position: TokenPosition) -> ParserResult<Expression> {
         match self.traveler.current().token_type {
             TokenType::IntLiteral    |
             TokenType::FloatLiteral  |
             TokenType::BoolLiteral   |
             TokenType::StringLiteral |
             TokenType::CharLiteral   |
-            TokenType::Identifier => self.call(callee),
+            TokenType::Identifier => self.call(callee, position),
             TokenType::Symbol     => match self.traveler.current_content().as_str() {
-                "(" => self.call(callee),
+                "(" => self.call(callee, position),
                 _ => Ok(callee),
             },
 
@@ -119,52 +124,44 @@ impl Parser {
         }
     }
 
+    // the statements between an indent and the dedent closing it
     fn block(&mut self) -> ParserResult<Expression> {
+        if self.traveler.current().token_type != TokenType::Indent {
+            return Err(ParserError::new_pos(self.traveler.current().position, &format!("expected an indented block, found: {}", self.traveler.current_content())))
+        }
+
+        self.traveler.next();
+
         let mut stack = Vec::new();
+
         loop {
-            if self.traveler.current().token_type == TokenType::Indent {
-                self.traveler.next();
-                if self.traveler.current_content() == "\n" {
-                    self.traveler.next();
-                    break
-                }
-            } else if self.traveler.current_content() == "\n" {
-                stack.push(self.traveler.current().clone());
-                self.traveler.next();
+            self.skip_whitespace()?;
 
-                if self.traveler.current().token_type == TokenType::Indent {
-                    self.traveler.next();
-                } else {
-                    break
-                }
+            if self.traveler.current().token_type == TokenType::Dedent {
+                self.traveler.next();
+                break
             }
 
             if self.traveler.remaining() < 2 {
                 break
             }
 
-            stack.push(self.traveler.current().clone());
-            self.traveler.next();
+            stack.push(self.statement()?)
         }
-    
-        let mut parser = Parser::new(Traveler::new(stack));
 
-        match parser.parse() {
-            Ok(s)    => Ok(Expression::Block(s)),
-            Err(why) => Err(ParserError::new(&format!("{}", why))),
-        }
+        Ok(Expression::Block(stack))
     }
 
     fn body(&mut self) -> ParserResult<Expression> {
         if self.traveler.current_content() == "\n" {
-            self.traveler.next();
+            self.skip_whitespace()?;
             self.block()
         } else {
             self.expression()
         }
     }
 
-    fn index(&mut self, id: Rc<Expression>) -> ParserResult<Expression> {
+    fn index(&mut self, id: Rc<Expression>, position: TokenPosition) -> ParserResult<Expression> {
         self.traveler.next();
 
         let index = Rc::new(self.expression()?);
@@ -172,43 +169,174 @@ impl Parser {
         self.traveler.expect_content("]")?;
         self.traveler.next();
 
+        let span = id.span().to(self.traveler.previous().span);
+
         Ok(
             Expression::Index(
                 Index {
                     id,
                     index,
-                    position: self.traveler.current().position
+                    position,
+                    span,
                 }
             )
         )
     }
     
     fn arm(&mut self) -> ParserResult<Expression> {
+        let position = self.traveler.current().position;
+        let start    = self.traveler.current().span;
+
         self.traveler.expect_content("|")?;
         self.traveler.next();
         
         let mut params = Vec::new();
         
-        while self.traveler.current_content() != "|" {
-            params.push(Rc::new(self.expression()?));
+        while self.traveler.current_content() != "|" && self.traveler.current_content() != "if" {
+            params.push(self.pattern()?);
 
-            if self.traveler.current_content() != "|" {
+            if self.traveler.current_content() != "|" && self.traveler.current_content() != "if" {
                 self.traveler.expect_content(",")?;
                 self.traveler.next();
             }
         }
 
+        let guard = if self.traveler.current_content() == "if" {
+            self.traveler.next();
+            Some(Rc::new(self.expression()?))
+        } else {
+            None
+        };
+
         self.traveler.expect_content("|")?;
         self.traveler.next();
 
         let body = Rc::new(self.body()?);
-        
+        let span = start.to(body.span());
+
         self.skip_whitespace()?;
         
-        Ok(Expression::Arm(Arm {params, body, position: self.traveler.current().position}))
+        Ok(Expression::Arm(Arm {params, guard, body, position, span}))
+    }
+
+    fn pattern(&mut self) -> ParserResult<Pattern> {
+        let position = self.traveler.current().position;
+
+        match self.traveler.current().token_type {
+            TokenType::IntLiteral    |
+            TokenType::FloatLiteral  |
+            TokenType::BoolLiteral   |
+            TokenType::StringLiteral |
+            TokenType::CharLiteral   => {
+                let literal = Rc::new(self.pattern_literal()?);
+
+                if self.traveler.current_content() != ".." {
+                    return Ok(Pattern::Literal(literal))
+                }
+
+                self.traveler.next();
+
+                match self.traveler.current().token_type {
+                    TokenType::IntLiteral | TokenType::FloatLiteral | TokenType::CharLiteral => {
+                        Ok(Pattern::Range(literal, Rc::new(self.pattern_literal()?)))
+                    },
+
+                    _ => Err(ParserError::new_pos(self.traveler.current().position, &format!("expected range end, found: {}", self.traveler.current_content()))),
+                }
+            },
+
+            TokenType::Identifier => {
+                let name = Rc::new(self.traveler.current_content());
+                self.traveler.next();
+
+                if self.traveler.current_content() == "@" {
+                    self.traveler.next();
+                    Ok(Pattern::As(name, Rc::new(self.pattern()?), position))
+                } else if *name == "_" {
+                    Ok(Pattern::Wildcard)
+                } else {
+                    Ok(Pattern::Bind(name, position))
+                }
+            },
+
+            TokenType::Symbol => match self.traveler.current_content().as_str() {
+                "(" => {
+                    self.traveler.next();
+
+                    let mut items = Vec::new();
+                    let mut comma = false;
+
+                    while self.traveler.current_content() != ")" {
+                        items.push(self.pattern()?);
+
+                        if self.traveler.current_content() != ")" {
+                            self.traveler.expect_content(",")?;
+                            self.traveler.next();
+                            comma = true
+                        }
+                    }
+
+                    self.traveler.next();
+
+                    if items.len() == 1 && !comma {
+                        Ok(items.pop().unwrap())
+                    } else {
+                        Ok(Pattern::Tuple(items))
+                    }
+                },
+
+                "[" => {
+                    self.traveler.next();
+
+                    let mut items = Vec::new();
+                    let mut rest  = None;
+
+                    while self.traveler.current_content() != "]" {
+                        if self.traveler.current_content() == ".." {
+                            self.traveler.next();
+
+                            rest = if self.traveler.current().token_type == TokenType::Identifier {
+                                Some(Rc::new(self.pattern()?))
+                            } else {
+                                Some(Rc::new(Pattern::Wildcard))
+                            };
+
+                            self.traveler.expect_content("]")?;
+                            break
+                        }
+
+                        items.push(self.pattern()?);
+
+                        if self.traveler.current_content() != "]" {
+                            self.traveler.expect_content(",")?;
+                            self.traveler.next();
+                        }
+                    }
+
+                    self.traveler.next();
+
+                    Ok(Pattern::List(items, rest))
+                },
+
+                _ => Err(ParserError::new_pos(position, &format!("expected pattern, found: {}", self.traveler.current_content()))),
+            },
+
+            _ => Err(ParserError::new_pos(position, &format!("expected pattern, found: {}", self.traveler.current_content()))),
+        }
+    }
+    
+    // patterns compare by value and take their width from what they match, so a suffix
+    // only has to fit
+    fn pattern_literal(&mut self) -> ParserResult<Expression> {
+        match self.term()? {
+            Expression::Convert(ref convert) => Ok((*convert.value).clone()),
+            literal                          => Ok(literal),
+        }
     }
     
     fn function(&mut self) -> ParserResult<Expression> {
+        let position = self.traveler.current().position;
+        let start    = self.traveler.current().span;
         self.traveler.next();
 
         self.skip_whitespace()?;
@@ -216,6 +344,10 @@ impl Parser {
         let mut arms = Vec::new();
         
         while self.traveler.current_content() != "}" {            
+            if self.traveler.remaining() < 2 {
+                return Err(ParserError::new_pos(position, "unclosed `{`"))
+            }
+
             if self.traveler.current_content() == "|" {
                 arms.push(Rc::new(self.arm()?))
             } else {
@@ -226,7 +358,9 @@ impl Parser {
         self.traveler.expect_content("}")?;
         self.traveler.next();
 
-        Ok(Expression::Function(Function{arms, position: self.traveler.current().position}))
+        let span = start.to(self.traveler.previous().span);
+
+        Ok(Expression::Function(Function{arms, position, span}))
     }
 
     pub fn term(&mut self) -> ParserResult<Expression> {
@@ -234,46 +368,43 @@ impl Parser {
             return Ok(Expression::EOF)
         }
 
-        match self.traveler.current().token_type {
-            TokenType::IntLiteral    => {
-                let a = Ok(Expression::Int(self.traveler.current_content().parse::<i64>().unwrap()));
-                self.traveler.next();
-                a
-            }
+        let position = self.traveler.current().position;
+        let start    = self.traveler.current().span;
 
-            TokenType::FloatLiteral  => {
-                let a = Ok(Expression::Float(self.traveler.current_content().parse::<f64>().unwrap()));
+        match self.traveler.current().token_type {
+            TokenType::IntLiteral | TokenType::FloatLiteral => {
+                let a = self.number();
                 self.traveler.next();
-                a
+                Ok(a)
             }
 
             TokenType::BoolLiteral   => {
-                let a = Ok(Expression::Bool(self.traveler.current_content() == "true"));
+                let a = Ok(Expression::Bool(self.traveler.current_content() == "true", start));
                 self.traveler.next();
                 a
             }
 
             TokenType::StringLiteral => {
-                let a = Ok(Expression::Str(Rc::new(self.traveler.current_content().clone())));
+                let a = Ok(Expression::Str(Rc::new(self.traveler.current_content().clone()), start));
                 self.traveler.next();
                 a
             }
 
             TokenType::CharLiteral => {
-                let a = Ok(Expression::Char(self.traveler.current_content().clone().remove(0)));
+                let a = Ok(Expression::Char(self.traveler.current_content().clone().remove(0), start));
                 self.traveler.next();
                 a
             }
 
             TokenType::Identifier => {
-                let a = Expression::Identifier(Rc::new(self.traveler.current_content().clone()), self.traveler.current().position);
+                let a = Expression::Identifier(Rc::new(self.traveler.current_content().clone()), position, start);
                 self.traveler.next();
 
                 if self.traveler.remaining() > 1 {
                     match self.traveler.current_content().as_str() {
                         "," | ")" => Ok(a),
-                        "["       => self.index(Rc::new(a)),
-                        _         => self.try_call(a),
+                        "["       => self.index(Rc::new(a), position),
+                        _         => self.try_call(a, position),
                     }
                 } else {
                     Ok(a)
@@ -283,25 +414,71 @@ impl Parser {
             TokenType::Symbol => match self.traveler.current_content().as_str() {
                 "(" => {
                     self.traveler.next();
-                    
-                    let a = self.expression()?;
 
-                    self.skip_whitespace()?;
+                    let a = if self.traveler.current_content() == ")" {
+                        Expression::Tuple(Vec::new(), start.to(self.traveler.current().span))
+                    } else {
+                        let first = self.expression()?;
+
+                        self.skip_whitespace()?;
+
+                        if self.traveler.current_content() == "," {
+                            let mut items = vec![Rc::new(first)];
+
+                            while self.traveler.current_content() == "," {
+                                self.traveler.next();
+                                items.push(Rc::new(self.expression()?));
+                                self.skip_whitespace()?;
+                            }
+
+                            Expression::Tuple(items, start.to(self.traveler.current().span))
+                        } else {
+                            first
+                        }
+                    };
+
                     self.traveler.expect_content(")")?;
                     self.traveler.next();
 
                     if self.traveler.current_content() == "[" {
-                        self.index(Rc::new(a))
+                        self.index(Rc::new(a), position)
                     } else if self.traveler.remaining() > 1 {
-                        self.try_call(a)
+                        self.try_call(a, position)
                     } else {
                         Ok(a)
                     }
                 }
+                "[" => {
+                    self.traveler.next();
+
+                    let mut items = Vec::new();
+
+                    self.skip_whitespace()?;
+
+                    while self.traveler.current_content() != "]" {
+                        items.push(Rc::new(self.expression()?));
+                        self.skip_whitespace()?;
+
+                        if self.traveler.current_content() != "]" {
+                            self.traveler.expect_content(",")?;
+                            self.traveler.next();
+                            self.skip_whitespace()?;
+                        }
+                    }
+
+                    self.traveler.next();
+
+                    Ok(Expression::List(items, start.to(self.traveler.previous().span)))
+                },
                 "{" => self.function(),
                 _ => Err(ParserError::new_pos(self.traveler.current().position, &format!("unexpected symbol: {}", self.traveler.current_content()))),
             },
 
+            TokenType::Type => match Type::from(&self.traveler.current()) {
+                Some(ref t) if t.is_numeric() => self.convert(t.clone()),
+                _ => Err(ParserError::new_pos(self.traveler.current().position, &format!("unexpected type: {}", self.traveler.current_content()))),
+            },
+
             TokenType::Keyword => match self.traveler.current_content().as_str() {
                 "print" => self.print(),
                 _ => Err(ParserError::new_pos(self.traveler.current().position, &format!("unexpected keyword: {}", self.traveler.current_content()))),
@@ -311,27 +488,52 @@ impl Parser {
         }
     }
 
-    fn assignment(&mut self, left: Rc<Expression>) -> ParserResult<Statement> {
+    // a number literal, converted to the width its suffix gives it, `255u08`
+    fn number(&self) -> Expression {
+        let token   = self.traveler.current();
+        let content = token.content();
+        let split   = content.find(|c| c == 'i' || c == 'u' || c == 'f').unwrap_or(content.len());
+
+        let (n, suffix) = content.split_at(split);
+
+        let value = match token.token_type {
+            TokenType::IntLiteral => match n.parse::<i128>() {
+                Ok(n)  => Expression::Int(n, token.span),
+                Err(_) => Expression::UInt(n.parse::<u128>().unwrap(), token.span),
+            },
+            _                     => Expression::Float(n.parse::<f64>().unwrap(), token.span),
+        };
+
+        match Type::named(suffix) {
+            Some(t) => Expression::Convert(Convert { t, value: Rc::new(value), position: token.position, span: token.span }),
+            None    => value,
+        }
+    }
+
+    // `position` is where the assigned name is
+    fn assignment(&mut self, left: Rc<Expression>, position: TokenPosition) -> ParserResult<Statement> {
         self.traveler.next();
 
         if self.traveler.current_content() == "\n" {
             Err(ParserError::new_pos(self.traveler.current().position, &format!("expected expression, found: {:?}", self.traveler.current_content())))
         } else {
             let right = Rc::new(self.expression()?);
+            let span  = left.span().to(right.span());
 
             Ok(
                 Statement::Assignment(
                     Assignment {
                         left,
                         right,
-                        position: self.traveler.current().position
+                        position,
+                        span,
                     }
                 )
             )
         }
     }
 
-    fn definition(&mut self, name: Rc<Expression>) -> ParserResult<Statement> {
+    fn definition(&mut self, name: Rc<Expression>, position: TokenPosition) -> ParserResult<Statement> {
         self.traveler.expect_content(":")?;
         self.traveler.next();
         
@@ -347,24 +549,42 @@ impl Parser {
             self.skip_whitespace()?;
         }
 
+        // a definition without a value ends with its name's `:` or its type
+        let mut span = name.span().to(self.traveler.previous().span);
+
         if self.traveler.current_content() == "=" {
             self.traveler.next();
 
-            let right = Some(Rc::new(self.expression()?));
+            let right = Rc::new(self.expression()?);
+
+            span = span.to(right.span());
 
-            Ok(Statement::Definition(Definition { t, name, right, position: self.traveler.current().position }))
+            Ok(Statement::Definition(Definition { t, name, right: Some(right), position, span }))
 
         } else {
-            Ok(Statement::Definition(Definition { t, name, right: None, position: self.traveler.current().position }))
+            Ok(Statement::Definition(Definition { t, name, right: None, position, span }))
         }
     }
     
+    fn convert(&mut self, t: Type) -> ParserResult<Expression> {
+        let position = self.traveler.current().position;
+        let start    = self.traveler.current().span;
+        self.traveler.next();
+
+        let value = Rc::new(self.expression()?);
+        let span  = start.to(value.span());
+
+        Ok(Expression::Convert(Convert { t, value, position, span }))
+    }
+
     fn print(&mut self) -> ParserResult<Expression> {
+        let start = self.traveler.current().span;
         self.traveler.next();
         
         let argument = Rc::new(self.expression()?);
+        let span     = start.to(argument.span());
         
-        Ok(Expression::Print(argument))
+        Ok(Expression::Print(argument, span))
     }
 
     fn statement(&mut self) -> ParserResult<Statement> {
@@ -378,13 +598,14 @@ impl Parser {
                 _ => Ok(Statement::Expression(Rc::new(self.expression()?))),
             },
             TokenType::Identifier => {
-                let a = Expression::Identifier(Rc::new(self.traveler.current_content().clone()), self.traveler.current().position);
+                let position = self.traveler.current().position;
+                let a = Expression::Identifier(Rc::new(self.traveler.current_content().clone()), position, self.traveler.current().span);
                 self.traveler.next();
 
                 if self.traveler.current_content() == "=" {
-                    self.assignment(Rc::new(a))
+                    self.assignment(Rc::new(a), position)
                 } else if self.traveler.current_content() == ":" {
-                    self.definition(Rc::new(a))
+                    self.definition(Rc::new(a), position)
                 } else {
                     self.traveler.prev();
                     Ok(Statement::Expression(Rc::new(self.expression()?)))
@@ -397,7 +618,7 @@ impl Parser {
         }
     }
 
-    fn call(&mut self, caller: Expression) -> ParserResult<Expression> {
+    fn call(&mut self, caller: Expression, position: TokenPosition) -> ParserResult<Expression> {
         let mut args = Vec::new();
 
         let mut acc = 0;
@@ -434,12 +655,15 @@ impl Parser {
             acc += 1
         }
 
+        let span = args.last().map_or(caller.span(), |arg| caller.span().to(arg.span()));
+
         Ok(
             Expression::Call(
                 Call {
                     callee: Rc::new(caller),
                     args,
-                    position: self.traveler.current().position
+                    position,
+                    span,
                 }
             )
         )
@@ -447,9 +671,11 @@ impl Parser {
 
     fn operation(&mut self, expression: Expression) -> ParserResult<Expression> {
         let mut ex_stack = vec![expression];
-        let mut op_stack: Vec<(Operand, u8)> = Vec::new();
+        // each operator keeps where it was written, which is where its operation is reported
+        let mut op_stack: Vec<(Operand, u8, TokenPosition)> = Vec::new();
 
-        op_stack.push(Operand::from_str(&self.traveler.current_content()).unwrap());
+        let (op, precedence) = Operand::from_str(&self.traveler.current_content()).unwrap();
+        op_stack.push((op, precedence, self.traveler.current().position));
         self.traveler.next();
 
         if self.traveler.current_content() == "\n" {
@@ -470,19 +696,23 @@ impl Parser {
                 }
 
                 let (op, precedence) = Operand::from_str(&self.traveler.current_content()).unwrap();
+                let position         = self.traveler.current().position;
                 self.traveler.next();
 
                 if precedence >= op_stack.last().unwrap().1 {
                     let left  = ex_stack.pop().unwrap();
                     let right = ex_stack.pop().unwrap();
+                    let (top, _, top_position) = op_stack.pop().unwrap();
+                    let span = right.span().to(left.span());
 
                     ex_stack.push(
                         Expression::Operation(
                             Operation {
                                 right: Rc::new(left),
-                                op:    op_stack.pop().unwrap().0,
+                                op:    top,
                                 left:  Rc::new(right),
-                                position: self.traveler.current().position
+                                position: top_position,
+                                span,
                             }
                         )
                     );
@@ -490,7 +720,7 @@ impl Parser {
                     let term = self.term()?;
 
                     ex_stack.push(term);
-                    op_stack.push((op, precedence));
+                    op_stack.push((op, precedence, position));
 
                     continue
                 }
@@ -498,19 +728,22 @@ impl Parser {
                 let term = self.term()?;
 
                 ex_stack.push(term);
-                op_stack.push((op, precedence));
+                op_stack.push((op, precedence, position));
             }
 
             let left  = ex_stack.pop().unwrap();
             let right = ex_stack.pop().unwrap();
+            let (op, _, position) = op_stack.pop().unwrap();
+            let span = right.span().to(left.span());
 
             ex_stack.push(
                 Expression::Operation(
                     Operation {
                         right: Rc::new(left),
-                        op:    op_stack.pop().unwrap().0,
+                        op,
                         left:  Rc::new(right),
-                        position: self.traveler.current().position,
+                        position,
+                        span,
                     }
                 )
             );
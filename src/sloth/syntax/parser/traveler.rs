@@ -43,6 +43,11 @@ impl Traveler {
         &self.tokens[self.top]
     }
 
+    // the token last moved past, where whatever was just parsed ends
+    pub fn previous(&self) -> &Token {
+        &self.tokens[self.top.max(1).min(self.tokens.len()) - 1]
+    }
+
     pub fn get(&self, i: usize) -> &Token {
         assert!(i > 0 && i < self.tokens.len(), "trying to get non-existing token");
         &self.tokens[i]
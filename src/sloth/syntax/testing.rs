@@ -0,0 +1,48 @@
+// what the tests of each stage need from the stages before it
+use std::rc::Rc;
+
+use super::*;
+
+pub fn tokens(source: &str) -> LexResult<Vec<Token>> {
+    Layout::new(lexer(0, &mut source.chars())).collect()
+}
+
+pub fn parse(source: &str) -> Vec<Statement> {
+    Parser::new(Traveler::new(tokens(source).unwrap())).parse().unwrap()
+}
+
+// the type the source is worth, with the warnings it raised
+pub fn check(source: &str) -> (CheckResult<Type>, Vec<CheckError>) {
+    let sym = Rc::new(SymTab::new_global());
+    let env = Rc::new(TypeTab::new_global());
+
+    let t = Expression::Block(parse(source)).get_type(&sym, &env).map(|t| env.generalize(&t));
+
+    (t, sym.take_warnings())
+}
+
+pub fn compile(vm: &mut VirtualMachine, source: &str) -> CompileResult<CompiledBlock> {
+    Compiler::new(vm.clone()).compile(&Expression::Block(parse(source)))
+}
+
+// what the source's last expression is worth, it has to pass the checker first. the
+// heap goes with the vm, so a value pointing into it is only good inside `then`
+pub fn run_then<T, F: FnOnce(Value) -> T>(source: &str, then: F) -> RunResult<T> {
+    if let (Err(err), _) = check(source) {
+        panic!("{:?}", err)
+    }
+
+    let mut vm = VirtualMachine::new();
+    let compiled = compile(&mut vm, source).unwrap();
+
+    vm.execute(&compiled).map(then)
+}
+
+pub fn run(source: &str) -> RunResult<Value> {
+    run_then(source, |value| value)
+}
+
+// the source's last expression as it prints
+pub fn shown(source: &str) -> RunResult<String> {
+    run_then(source, |value| format!("{}", value))
+}